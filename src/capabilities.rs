@@ -0,0 +1,125 @@
+use crossterm::{cursor, style::Color};
+use std::env;
+use std::io::{self, IsTerminal};
+
+/// How many colors the terminal can show.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorDepth {
+    /// No colors at all, for `NO_COLOR`, dumb terminals and output that is not a terminal.
+    None,
+    Ansi16,
+    Ansi256,
+    TrueColor,
+}
+
+/// The 16 standard colors in the order of their ANSI numbers, with their usual RGB values.
+const ANSI16: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::DarkRed, (128, 0, 0)),
+    (Color::DarkGreen, (0, 128, 0)),
+    (Color::DarkYellow, (128, 128, 0)),
+    (Color::DarkBlue, (0, 0, 128)),
+    (Color::DarkMagenta, (128, 0, 128)),
+    (Color::DarkCyan, (0, 128, 128)),
+    (Color::Grey, (192, 192, 192)),
+    (Color::DarkGrey, (128, 128, 128)),
+    (Color::Red, (255, 0, 0)),
+    (Color::Green, (0, 255, 0)),
+    (Color::Yellow, (255, 255, 0)),
+    (Color::Blue, (0, 0, 255)),
+    (Color::Magenta, (255, 0, 255)),
+    (Color::Cyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// Levels of the 6x6x6 color cube in the 256 color palette.
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// RGB value of a color from the 256 color palette.
+fn ansi256_rgb(value: u8) -> (u8, u8, u8) {
+    match value {
+        0..=15 => ANSI16[value as usize].1,
+        16..=231 => {
+            let value = value - 16;
+            (
+                CUBE[(value / 36) as usize],
+                CUBE[(value / 6 % 6) as usize],
+                CUBE[(value % 6) as usize],
+            )
+        }
+        _ => {
+            let grey = 8 + (value - 232) * 10;
+            (grey, grey, grey)
+        }
+    }
+}
+
+fn nearest_ansi16((r, g, b): (u8, u8, u8)) -> Color {
+    let distance = |(cr, cg, cb): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (i32::from(a) - i32::from(b)).pow(2);
+        d(r, cr) + d(g, cg) + d(b, cb)
+    };
+    ANSI16
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb))
+        .map_or(Color::White, |&(color, _)| color)
+}
+
+fn nearest_ansi256((r, g, b): (u8, u8, u8)) -> Color {
+    let level = |c: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| (i32::from(CUBE[i]) - i32::from(c)).abs())
+            .unwrap_or(0) as u8
+    };
+    Color::AnsiValue(16 + 36 * level(r) + 6 * level(g) + level(b))
+}
+
+impl ColorDepth {
+    /// The closest color this depth can show, `None` when colors are off.
+    pub fn map(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (ColorDepth::None, _) => None,
+            (ColorDepth::TrueColor, color) => Some(color),
+            (ColorDepth::Ansi256, Color::Rgb { r, g, b }) => Some(nearest_ansi256((r, g, b))),
+            (ColorDepth::Ansi16, Color::Rgb { r, g, b }) => Some(nearest_ansi16((r, g, b))),
+            (ColorDepth::Ansi16, Color::AnsiValue(value)) => {
+                Some(nearest_ansi16(ansi256_rgb(value)))
+            }
+            (_, color) => Some(color),
+        }
+    }
+}
+
+/// What the terminal supports, detected once at startup.
+#[derive(Clone, Copy, Debug)]
+pub struct Capabilities {
+    pub colors: ColorDepth,
+    /// Raw mode editing works, otherwise lines are read in cooked mode.
+    pub interactive: bool,
+}
+impl Capabilities {
+    pub fn detect() -> Self {
+        let term = env::var("TERM").unwrap_or_default();
+        let colorterm = env::var("COLORTERM").unwrap_or_default();
+        let dumb = term == "dumb";
+        let terminal = io::stdin().is_terminal() && io::stdout().is_terminal();
+
+        // https://no-color.org: any non-empty value turns colors off.
+        let no_color = env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let colors = if no_color || dumb || !io::stdout().is_terminal() {
+            ColorDepth::None
+        } else if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorDepth::TrueColor
+        } else if term.contains("256color") {
+            ColorDepth::Ansi256
+        } else {
+            ColorDepth::Ansi16
+        };
+
+        Capabilities {
+            colors,
+            // Only ask for the cursor position when there is a chance of an answer.
+            interactive: terminal && !dumb && cursor::position().is_ok(),
+        }
+    }
+}
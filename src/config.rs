@@ -0,0 +1,87 @@
+use super::keymap::Action;
+use super::prompt::DEFAULT_PROMPT;
+use super::theme::Theme;
+use super::words::WordStyle;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Settings kept between sessions, stored as JSON in `$CRISPY_CONFIG` or `~/.crispy.json`.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub macros: BTreeMap<String, Vec<Action>>,
+    /// Key chord in emacs notation, see `Key::parse_sequence`.
+    pub bindings: BTreeMap<String, Action>,
+    /// How word motions and word edits split the line.
+    pub words: WordStyle,
+    /// Binds `C-u` to universal-argument like emacs instead of unix-line-discard.
+    pub universal_argument: bool,
+    /// Template for the prompt, see `PromptContext::render`.
+    pub prompt: String,
+    /// Template for the prompt shown at the right edge, empty for none.
+    pub right_prompt: String,
+    /// Name of a theme from `themes` or a built-in one.
+    pub theme: String,
+    pub themes: BTreeMap<String, Theme>,
+    /// Alias names without the `:` and the command lines they stand for.
+    pub aliases: BTreeMap<String, String>,
+    /// Option names and values like in `:option`.
+    pub options: BTreeMap<String, String>,
+    /// Set when the file could not be loaded, saving would replace it with the defaults.
+    #[serde(skip)]
+    pub unreadable: bool,
+}
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            macros: BTreeMap::new(),
+            bindings: BTreeMap::new(),
+            words: WordStyle::default(),
+            universal_argument: false,
+            prompt: String::from(DEFAULT_PROMPT),
+            right_prompt: String::new(),
+            theme: String::from("dark"),
+            themes: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            options: BTreeMap::new(),
+            unreadable: false,
+        }
+    }
+}
+impl Config {
+    fn path() -> Option<PathBuf> {
+        match env::var_os("CRISPY_CONFIG") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => env::var_os("HOME").map(|home| PathBuf::from(home).join(".crispy.json")),
+        }
+    }
+
+    /// A missing file is an empty config.
+    pub fn load() -> io::Result<Config> {
+        match Config::path() {
+            Some(path) if path.exists() => Ok(serde_json::from_str(&fs::read_to_string(path)?)?),
+            _ => Ok(Config::default()),
+        }
+    }
+
+    /// Refuses to overwrite a file that could not be loaded.
+    pub fn save(&self) -> io::Result<()> {
+        if self.unreadable {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "it could not be loaded, fix it and restart",
+            ));
+        }
+        match Config::path() {
+            Some(path) => fs::write(path, serde_json::to_string_pretty(self)?),
+            None => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "neither CRISPY_CONFIG nor HOME is set",
+            )),
+        }
+    }
+}
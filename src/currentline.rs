@@ -4,17 +4,24 @@ use crossterm::{
     ExecutableCommand,
 };
 use std::io::{self, Write};
+use unicode_width::UnicodeWidthStr;
+
+use super::highlight::Highlighter;
+use super::prompt::{Rendered, DEFAULT_PROMPT};
+use super::theme::Theme;
+use super::words::WordStyle;
+/// `x` counts characters into the line, `y` is the row of the terminal it is on.
 #[derive(Debug)]
 pub struct Position {
-    x: u16,
+    x: usize,
     y: u16,
 }
 impl Position {
-    pub fn x(&self) -> u16 {
+    pub fn x(&self) -> usize {
         self.x
     }
 
-    pub fn set_x(&mut self, x: u16) {
+    pub fn set_x(&mut self, x: usize) {
         self.x = x;
     }
 
@@ -35,17 +42,35 @@ impl Position {
     pub fn move_right(&mut self) {
         self.x += 1;
     }
-
-    pub fn down(&mut self) {
-        self.y += 1;
-    }
+}
+pub enum Case {
+    Upper,
+    Lower,
+    Capital,
+}
+/// State of the line before an edit, restored by `undo`.
+#[derive(Debug)]
+pub struct Snapshot {
+    leftbuffer: String,
+    rightbuffer: String,
+    x: usize,
 }
 #[derive(Debug)]
 pub struct CurrentLine {
     pub position: Position,
     leftbuffer: String,
     rightbuffer: String,
-    history: Vec<String>,
+    highlighter: Highlighter,
+    suggestion: Option<String>,
+    undo: Vec<Snapshot>,
+    overwrite: bool,
+    word_style: WordStyle,
+    prompt: Rendered,
+    right_prompt: Rendered,
+    theme: Theme,
+    /// Alias names, highlighted like the built-in commands.
+    aliases: Vec<String>,
+    mark: Option<usize>,
 }
 impl CurrentLine {
     #[allow(dead_code)]
@@ -60,82 +85,343 @@ impl CurrentLine {
     pub fn clear(&mut self) {
         self.leftbuffer = String::new();
         self.rightbuffer = String::new();
+        self.suggestion = None;
+        self.undo.clear();
+        self.mark = None;
     }
 
-    pub fn new(x: u16, y: u16) -> Self {
+    pub fn new(x: usize, y: u16) -> Self {
         CurrentLine {
             position: Position { x, y },
             leftbuffer: String::new(),
             rightbuffer: String::new(),
-            history: Vec::new(),
+            highlighter: Highlighter::new(),
+            suggestion: None,
+            undo: Vec::new(),
+            overwrite: false,
+            word_style: WordStyle::default(),
+            prompt: Rendered::plain(DEFAULT_PROMPT),
+            right_prompt: Rendered::default(),
+            theme: Theme::default(),
+            aliases: Vec::new(),
+            mark: None,
         }
     }
 
-    pub fn position_down(&mut self) {
-        self.position.down();
-    }
-
     pub fn delete_left(&mut self) {
         self.leftbuffer.pop();
         self.position.move_left();
     }
 
-    pub fn delete_right(&mut self) {
-        if self.rightbuffer.len() > 0 {
-            self.rightbuffer = self.rightbuffer.drain(1..).collect::<String>();
-            super::debug::debug_message("Delete one character from right buffer").unwrap();
+    pub fn delete_right(&mut self) -> bool {
+        if self.rightbuffer.is_empty() {
+            false
         } else {
-            super::debug::debug_message("Right buffer empty").unwrap();
+            self.rightbuffer.remove(0);
+            true
         }
     }
 
-    pub fn add_char(&mut self, c: char) -> io::Result<()> {
-        self.position.x += 1;
+    pub fn add_char(&mut self, c: char) {
+        self.position.move_right();
         self.leftbuffer.push(c);
-        self.display()
     }
 
-    pub fn display(&self) -> io::Result<()> {
-        io::stdout().execute(cursor::MoveTo(2, self.position.y()))?;
+    /// A typed character, replaces the one under the cursor in overwrite mode.
+    pub fn self_insert(&mut self, c: char) {
+        if self.overwrite && !self.rightbuffer.is_empty() {
+            self.rightbuffer.remove(0);
+        }
+        self.add_char(c);
+    }
+
+    pub fn toggle_overwrite(&mut self) -> bool {
+        self.overwrite = !self.overwrite;
+        self.overwrite
+    }
+
+    pub fn overwriting(&self) -> bool {
+        self.overwrite
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        text.chars().for_each(|c| self.add_char(c));
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            leftbuffer: self.leftbuffer.clone(),
+            rightbuffer: self.rightbuffer.clone(),
+            x: self.position.x(),
+        }
+    }
+
+    /// Whether the text changed since `snapshot` was taken, cursor moves do not count.
+    pub fn changed_since(&self, snapshot: &Snapshot) -> bool {
+        format!("{}{}", snapshot.leftbuffer, snapshot.rightbuffer) != self.collect()
+    }
+
+    pub fn push_undo(&mut self, snapshot: Snapshot) {
+        if self.changed_since(&snapshot) {
+            self.undo.push(snapshot);
+        }
+    }
+
+    /// Text before the cursor.
+    pub fn left(&self) -> &str {
+        &self.leftbuffer
+    }
+
+    /// Text under and after the cursor.
+    pub fn right(&self) -> &str {
+        &self.rightbuffer
+    }
+
+    pub fn mark(&self) -> Option<usize> {
+        self.mark
+    }
+
+    pub fn undo_depth(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn set_mark(&mut self) {
+        self.mark = Some(self.position.x());
+    }
+
+    pub fn clear_mark(&mut self) {
+        self.mark = None;
+    }
+
+    /// Characters between the mark and the cursor, `None` without a mark.
+    pub fn region(&self) -> Option<(usize, usize)> {
+        let x = self.position.x();
+        self.mark.map(|mark| (mark.min(x), mark.max(x)))
+    }
+
+    pub fn region_text(&self) -> Option<String> {
+        let (start, end) = self.region()?;
+        Some(self.chars()[start..end].iter().collect())
+    }
+
+    pub fn exchange_point_and_mark(&mut self) -> bool {
+        match self.mark {
+            Some(mark) => {
+                self.mark = Some(self.position.x());
+                self.set_position_x(mark);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn undo(&mut self) -> bool {
+        match self.undo.pop() {
+            Some(snapshot) => {
+                self.leftbuffer = snapshot.leftbuffer;
+                self.rightbuffer = snapshot.rightbuffer;
+                self.position.set_x(snapshot.x);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Ghost text shown after the end of the line.
+    pub fn set_suggestion(&mut self, suggestion: Option<String>) {
+        self.suggestion = suggestion;
+    }
+
+    /// Inserts the suggestion, or only its next word, when the cursor is at the end of the line.
+    pub fn accept_suggestion(&mut self, word: bool) -> bool {
+        if !self.rightbuffer.is_empty() {
+            return false;
+        }
+        let suggestion = match self.suggestion.take() {
+            Some(suggestion) => suggestion,
+            None => return false,
+        };
+        let end = if word {
+            let start = suggestion.len() - suggestion.trim_start().len();
+            suggestion[start..]
+                .find(char::is_whitespace)
+                .map_or(suggestion.len(), |i| start + i)
+        } else {
+            suggestion.len()
+        };
+        self.insert_str(&suggestion[..end]);
+        true
+    }
+
+    pub fn set_prompt(&mut self, prompt: Rendered, right_prompt: Rendered) {
+        self.prompt = prompt;
+        self.right_prompt = right_prompt;
+    }
+
+    /// The prompt and the text, as the line is kept in the output.
+    pub fn transcript(&self) -> String {
+        format!(
+            "{}{}",
+            self.theme.prompt.apply(&self.prompt.text),
+            self.collect()
+        )
+    }
+
+    /// Columns taken by `text` once printed, newlines are shown as `↵`.
+    fn width(text: &str) -> usize {
+        text.width() + text.matches('\n').count()
+    }
+
+    pub fn display(&mut self) -> io::Result<()> {
+        io::stdout().execute(cursor::MoveTo(0, self.position.y()))?;
         io::stdout().execute(terminal::Clear(ClearType::UntilNewLine))?;
-        print!("\r> {}{}", self.leftbuffer, self.rightbuffer);
+        let text = self.collect();
+        let region = self.region().map(|(start, end)| {
+            let byte = |x: usize| text.char_indices().nth(x).map_or(text.len(), |(i, _)| i);
+            (byte(start), byte(end))
+        });
+        // Pasted newlines stay in the buffer but must not move the cursor down.
+        print!(
+            "\r{}{}",
+            self.theme.prompt.apply(&self.prompt.text),
+            self.highlighter
+                .highlight(&text, region, &self.theme, &self.aliases)
+                .replace('\n', "↵")
+        );
+        let mut used = usize::from(self.prompt.width) + CurrentLine::width(&text);
+        if let (true, Some(suggestion)) = (self.rightbuffer.is_empty(), &self.suggestion) {
+            print!("{}", self.theme.hint.apply(&suggestion.replace('\n', "↵")));
+            used += CurrentLine::width(suggestion);
+        }
+        // The right prompt gives way to long lines instead of overlapping them.
+        let (columns, _) = terminal::size()?;
+        let right_width = usize::from(self.right_prompt.width);
+        if right_width > 0 && used + right_width < usize::from(columns) {
+            io::stdout().execute(cursor::MoveToColumn(columns - self.right_prompt.width))?;
+            print!("{}", self.right_prompt.text);
+        }
         io::stdout().flush()?;
-        io::stdout().execute(cursor::MoveTo(self.position.x() + 2, self.position.y()))?;
+        // Past the right edge the terminal keeps the cursor in the last column.
+        let column = usize::from(self.prompt.width) + CurrentLine::width(&self.leftbuffer);
+        let last = columns.saturating_sub(1);
+        io::stdout().execute(cursor::MoveTo(
+            u16::try_from(column).map_or(last, |column| column.min(last)),
+            self.position.y(),
+        ))?;
 
         Ok(())
     }
 
-    pub fn clear_rightbuffer(&mut self) {
-        self.rightbuffer = String::new();
+    fn chars(&self) -> Vec<char> {
+        self.collect().chars().collect()
     }
 
-    pub fn push_rightbuffer_to_history(&mut self) {
-        self.history.push(self.rightbuffer.clone());
-        if self.history.len() > 0 {
-            let last: usize = self.history.len() - 1;
-            match super::debug::debug_message(self.history[last].as_str()) {
-                _ => (),
-            }
+    /// Replaces the text and puts the cursor before the character at `x`.
+    fn set_chars(&mut self, chars: &[char], x: usize) {
+        self.leftbuffer = chars[..x].iter().collect();
+        self.rightbuffer = chars[x..].iter().collect();
+        self.position.set_x(x);
+    }
+
+    /// Removes the characters from `start` up to `end` and returns them.
+    pub fn kill_range(&mut self, start: usize, end: usize) -> String {
+        let mut chars = self.chars();
+        let killed = chars.drain(start..end).collect::<String>();
+        self.set_chars(&chars, start);
+        killed
+    }
+
+    pub fn kill_to_end(&mut self) -> String {
+        self.kill_range(self.position.x(), self.length())
+    }
+
+    pub fn kill_to_start(&mut self) -> String {
+        self.kill_range(0, self.position.x())
+    }
+
+    /// Start of the whitespace separated word in front of the cursor, like unix-word-rubout.
+    pub fn unix_word_start(&self) -> usize {
+        let left = self.leftbuffer.chars().collect::<Vec<_>>();
+        let mut start = left.len();
+        while start > 0 && left[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        while start > 0 && !left[start - 1].is_whitespace() {
+            start -= 1;
+        }
+        start
+    }
+
+    /// Swaps the characters around the cursor, at the end of the line the last two.
+    pub fn transpose_chars(&mut self) -> bool {
+        let mut chars = self.chars();
+        let x = (self.position.x()).min(chars.len().saturating_sub(1));
+        if x == 0 {
+            return false;
         }
+        chars.swap(x - 1, x);
+        self.set_chars(&chars, x + 1);
+        true
     }
 
-    pub fn push_right_word_to_history(&mut self) {
-        if self.rightbuffer.len() > 0 {
-            match self.rightbuffer.find(' ') {
-                Some(index) => {
-                    let mut real_index = index;
-                    while real_index < self.rightbuffer.len() {
-                        if self.rightbuffer.chars().nth(real_index) != Some(' ') {
-                            break;
-                        }
-                        real_index += 1;
-                    }
-                    self.history
-                        .push(self.rightbuffer.drain(0..real_index).collect::<String>());
-                }
-                _ => (),
+    /// Swaps the word at or after the cursor with the one before it.
+    pub fn transpose_words(&mut self) -> bool {
+        let chars = self.chars();
+        let words = self.word_style.spans(&chars);
+        let x = self.position.x();
+        let second = words
+            .iter()
+            .position(|&(_, end)| end > x)
+            .unwrap_or(words.len().saturating_sub(1))
+            .max(1);
+        if words.len() < 2 {
+            return false;
+        }
+        let ((a_start, a_end), (b_start, b_end)) = (words[second - 1], words[second]);
+        let mut swapped = chars[..a_start].to_vec();
+        swapped.extend(&chars[b_start..b_end]);
+        swapped.extend(&chars[a_end..b_start]);
+        swapped.extend(&chars[a_start..a_end]);
+        swapped.extend(&chars[b_end..]);
+        self.set_chars(&swapped, b_end);
+        true
+    }
+
+    /// Changes the case from the cursor to the end of the next word and moves behind it.
+    pub fn change_case_word(&mut self, case: Case) -> bool {
+        let chars = self.chars();
+        let x = self.position.x();
+        let end = match self
+            .word_style
+            .spans(&chars)
+            .iter()
+            .find(|&&(_, end)| end > x)
+        {
+            Some(&(_, end)) => end,
+            None => return false,
+        };
+
+        let mut changed = chars[..x].to_vec();
+        let mut first = true;
+        for c in &chars[x..end] {
+            let upper = match case {
+                Case::Upper => true,
+                Case::Lower => false,
+                Case::Capital => first && c.is_alphanumeric(),
+            };
+            if c.is_alphanumeric() {
+                first = false;
+            }
+            if upper {
+                changed.extend(c.to_uppercase());
+            } else {
+                changed.extend(c.to_lowercase());
             }
         }
+        let new_end = changed.len();
+        changed.extend(&chars[end..]);
+        self.set_chars(&changed, new_end);
+        true
     }
 
     pub fn set_position_start_x(&mut self) {
@@ -149,141 +435,69 @@ impl CurrentLine {
     }
 
     pub fn set_position_end(&mut self) {
+        self.position.set_x(self.length());
         self.leftbuffer = self.collect();
-        self.position.set_x(self.leftbuffer.len() as u16);
         self.rightbuffer = String::new();
     }
 
-    pub fn set_position_x(&mut self, x: u16) {
-        let collection = self.collect();
-        let (left, right) = collection.split_at(x.into());
-        self.leftbuffer = left.to_string();
-        self.rightbuffer = right.to_string();
-        self.position.set_x(x);
+    pub fn set_position_x(&mut self, x: usize) {
+        let chars = self.chars();
+        self.set_chars(&chars, x);
     }
 
     pub fn move_left(&mut self) -> bool {
         if self.position.x == 0 {
             false
         } else {
-            self.position.move_left();
-            let collection = self.collect();
-            let (leftbuffer, rightbuffer) = collection.split_at(self.position.x.into());
-            self.leftbuffer = leftbuffer.to_string();
-            self.rightbuffer = rightbuffer.to_string();
+            self.set_position_x(self.position.x - 1);
             true
         }
     }
 
+    /// Number of characters on the line.
     pub fn length(&self) -> usize {
-        self.leftbuffer.len() + self.rightbuffer.len()
+        self.leftbuffer.chars().count() + self.rightbuffer.chars().count()
     }
 
     pub fn move_right(&mut self) -> bool {
-        if self.position.x == self.length() as u16 {
+        if self.position.x == self.length() {
             false
         } else {
-            self.position.move_right();
-            let collection = self.collect();
-            let (leftbuffer, rightbuffer) = collection.split_at(self.position.x.into());
-            self.leftbuffer = leftbuffer.to_string();
-            self.rightbuffer = rightbuffer.to_string();
+            self.set_position_x(self.position.x + 1);
             true
         }
     }
 
-    pub fn delete_word_right(&mut self) {
-        if self.rightbuffer.len() > 0 {
-            match self.rightbuffer.find(' ') {
-                Some(index) => {
-                    let mut real_index = index;
-                    while real_index < self.rightbuffer.len() {
-                        if self.rightbuffer.chars().nth(real_index) != Some(' ') {
-                            break;
-                        }
-                        real_index += 1;
-                    }
-                    super::debug::debug_message(
-                        format!("found space in right buffer: {}", index).as_str(),
-                    )
-                    .unwrap();
-                    self.rightbuffer = self.rightbuffer.drain(real_index..).collect::<String>();
-                }
-                _ => super::debug::debug_message("found space in right buffer").unwrap(),
-            }
-        }
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
     }
 
-    pub fn left_word(&self) -> Option<u16> {
+    pub fn set_aliases(&mut self, aliases: Vec<String>) {
+        self.aliases = aliases;
+    }
+
+    pub fn theme(&self) -> &Theme {
+        &self.theme
+    }
+
+    pub fn set_word_style(&mut self, word_style: WordStyle) {
+        self.word_style = word_style;
+    }
+
+    pub fn left_word(&self) -> Option<usize> {
         if self.position.x() == 0 {
             None
         } else {
-            let mut c = Vec::new();
-            self.collect().split(' ').fold(0, |mut acc, value| {
-                c.push(acc);
-                acc += value.to_string().len();
-                acc
-            });
-
-            let collection = self.collect();
-            let char_indeces = collection
-                .chars()
-                .enumerate()
-                .filter(|(_, c)| *c == ' ')
-                .collect::<Vec<_>>();
-
-            let mut res = vec![0];
-            char_indeces.iter().for_each(|(i, _)| {
-                let chars = collection.chars().collect::<Vec<_>>();
-                if chars.len() > i + 1 {
-                    let next = chars[i + 1];
-                    if next != ' ' {
-                        res.push(i + 1);
-                    }
-                }
-            });
-            match res
-                .iter()
-                .filter(|&&i| i < self.position.x() as usize)
-                .max()
-            {
-                Some(i) => Some(*i as u16),
-                _ => None,
-            }
+            let start = self
+                .word_style
+                .previous_start(&self.chars(), self.position.x());
+            Some(start.unwrap_or(0))
         }
     }
-    pub fn right_word(&self) -> Option<u16> {
-        let mut c = Vec::new();
-        self.collect().split(' ').fold(0, |mut acc, value| {
-            c.push(acc);
-            acc += value.to_string().len();
-            acc
-        });
-
-        let collection = self.collect();
-        let char_indeces = collection
-            .chars()
-            .enumerate()
-            .filter(|(_, c)| *c == ' ')
-            .collect::<Vec<_>>();
-
-        let mut res = vec![0];
-        char_indeces.iter().for_each(|(i, _)| {
-            let chars = collection.chars().collect::<Vec<_>>();
-            if chars.len() > i + 1 {
-                let next = chars[i + 1];
-                if next != ' ' {
-                    res.push(i + 1);
-                }
-            }
-        });
-        match res
-            .iter()
-            .filter(|&&i| i > self.position.x() as usize)
-            .min()
-        {
-            Some(i) => Some(*i as u16),
-            _ => Some(self.length() as u16),
-        }
+    pub fn right_word(&self) -> Option<usize> {
+        let start = self
+            .word_style
+            .next_start(&self.chars(), self.position.x());
+        Some(start)
     }
 }
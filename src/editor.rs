@@ -0,0 +1,350 @@
+use super::capabilities::Capabilities;
+use super::config::Config;
+use super::currentline::CurrentLine;
+use super::history::History;
+use super::inspector::Inspector;
+use super::keymap::{Action, Key, Keymap, Keymaps, Mode};
+use super::macros::Macros;
+use super::options::{BellStyle, CompletionStyle, EditingMode, LayoutStyle, Options, PagerStyle};
+use super::pager::Pager;
+use super::printer::Printer;
+use super::prompt::{PromptContext, Rendered};
+use super::recording::Input;
+use super::status::StatusBar;
+use super::suggest::{CommandSuggester, HistorySuggester, Sources, Suggester};
+use super::theme::Theme;
+use crossterm::event::{KeyCode, KeyModifiers};
+use crossterm::terminal;
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Numeric argument for the next action, typed with `M-<digits>`, `M--` or `C-u`.
+#[derive(Default)]
+pub struct Argument {
+    digits: Option<u32>,
+    negative: bool,
+    /// Each `C-u` without digits multiplies by four.
+    universal: u32,
+}
+impl Argument {
+    pub fn push_digit(&mut self, digit: u8) {
+        let digits = self.digits.unwrap_or(0) * 10 + u32::from(digit);
+        self.digits = Some(digits.min(MAX_ARGUMENT));
+    }
+
+    pub fn negate(&mut self) {
+        self.negative = !self.negative;
+    }
+
+    pub fn multiply(&mut self) {
+        if self.digits.is_none() {
+            self.universal += 1;
+        }
+    }
+
+    pub fn has_digits(&self) -> bool {
+        self.digits.is_some()
+    }
+
+    pub fn count(&self) -> i64 {
+        let count = match self.digits {
+            Some(digits) => i64::from(digits),
+            None => 4_i64.pow(self.universal.min(6)),
+        };
+        if self.negative {
+            -count
+        } else {
+            count
+        }
+    }
+}
+
+/// Largest numeric argument, more repetitions are not useful on a single line.
+const MAX_ARGUMENT: u32 = 9999;
+
+/// State of a session that outlives a single key event.
+pub struct Editor {
+    pub line: CurrentLine,
+    pub printer: Printer,
+    /// Where every key comes from, also for the pager and prompts outside the line.
+    pub input: Input,
+    pub keymaps: Keymaps,
+    /// Picks the keymap, it follows the editing mode option.
+    pub mode: Mode,
+    pub options: Options,
+    pub macros: Macros,
+    pub config: Config,
+    pub history: History,
+    /// Asked in order for the ghost text, the first suggestion wins.
+    pub suggesters: Vec<Box<dyn Suggester>>,
+    pub last_action: Option<Action>,
+    /// Killed text, most recent last.
+    pub kill_ring: Vec<String>,
+    pub argument: Option<Argument>,
+    /// Status of the last command, 0 means success.
+    pub last_status: i32,
+    /// Number of lines entered in this session.
+    pub count: usize,
+    pub capabilities: Capabilities,
+    /// Set with `:set`, used as `$name` in commands and `{name}` in the prompt.
+    pub variables: BTreeMap<String, String>,
+    /// Number of scripts that are running, they can load each other.
+    pub script_depth: usize,
+    /// Defined with `:alias` or in the config.
+    pub aliases: BTreeMap<String, String>,
+    /// Aliases whose bodies are running, an alias that shows up again is a loop.
+    pub expanding: Vec<String>,
+    pub status: StatusBar,
+    pub inspector: Inspector,
+}
+impl Editor {
+    pub fn new(line: CurrentLine, config: Config, capabilities: Capabilities) -> Self {
+        let mut keymaps = Keymaps::new();
+        if config.universal_argument {
+            keymaps.get(Mode::Emacs).bind(
+                vec![Key::new(KeyCode::Char('u'), KeyModifiers::CONTROL)],
+                Action::UniversalArgument,
+            );
+        }
+        Editor {
+            line,
+            printer: Printer::new(capabilities.interactive),
+            input: Input::default(),
+            keymaps,
+            mode: Mode::Emacs,
+            options: Options::default(),
+            macros: Macros::new(config.macros.clone()),
+            aliases: config.aliases.clone(),
+            config,
+            history: History::new(),
+            suggesters: vec![Box::new(HistorySuggester), Box::new(CommandSuggester)],
+            last_action: None,
+            kill_ring: Vec::new(),
+            argument: None,
+            last_status: 0,
+            count: 0,
+            capabilities,
+            variables: BTreeMap::new(),
+            script_depth: 0,
+            expanding: Vec::new(),
+            status: StatusBar::default(),
+            inspector: Inspector::default(),
+        }
+    }
+
+    /// Binds the chords from the config, returns the ones that could not be parsed.
+    pub fn load_bindings(&mut self) -> Vec<String> {
+        let mut invalid = Vec::new();
+        for (chord, action) in &self.config.bindings {
+            match Key::parse_sequence(chord) {
+                Some(keys) => self.keymaps.bind(keys, action.clone()),
+                None => invalid.push(chord.clone()),
+            }
+        }
+        invalid
+    }
+
+    /// Sets the options from the config, returns the invalid ones with the reason.
+    pub fn load_options(&mut self) -> io::Result<Vec<String>> {
+        let (options, invalid) = Options::from_config(&self.config);
+        self.options = options;
+        self.apply_options()?;
+        Ok(invalid)
+    }
+
+    /// Makes changed options take effect.
+    pub fn apply_options(&mut self) -> io::Result<()> {
+        let mode = match (self.options.editing_mode, self.mode) {
+            (EditingMode::Emacs, _) => Mode::Emacs,
+            (EditingMode::Vi, Mode::Emacs) => Mode::ViInsert,
+            (EditingMode::Vi, mode) => mode,
+        };
+        self.mode = mode;
+        self.history.set_max(self.options.history_size);
+        self.line
+            .set_word_style(self.options.word_separators.clone());
+        self.printer.set_footer(self.footer());
+        let fullscreen = self.options.layout == LayoutStyle::Fullscreen;
+        self.printer.set_fullscreen(fullscreen, &mut self.line)
+    }
+
+    /// The keymap for the current mode.
+    pub fn keymap(&mut self) -> &mut Keymap {
+        self.keymaps.get(self.mode)
+    }
+
+    /// Signals that a key could not do anything, as the bell style option says.
+    pub fn bell(&mut self) -> io::Result<()> {
+        match self.options.bell_style {
+            BellStyle::None => Ok(()),
+            BellStyle::Audible => {
+                print!("\x07");
+                io::stdout().flush()
+            }
+            BellStyle::Visible => {
+                print!("\x1b[?5h");
+                io::stdout().flush()?;
+                thread::sleep(Duration::from_millis(100));
+                print!("\x1b[?5l");
+                io::stdout().flush()
+            }
+        }
+    }
+
+    /// Uses the theme named in the config, `false` if there is no such theme.
+    pub fn load_theme(&mut self) -> bool {
+        match Theme::named(&self.config.theme, &self.config.themes) {
+            Some(theme) => {
+                self.line
+                    .set_theme(theme.downgrade(self.capabilities.colors));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Lets the line highlight the aliases like commands.
+    pub fn sync_aliases(&mut self) {
+        self.line
+            .set_aliases(self.aliases.keys().cloned().collect());
+    }
+
+    /// Stores killed text, right after another kill it is added to that entry instead.
+    pub fn kill(&mut self, text: String, backward: bool) {
+        if text.is_empty() {
+            return;
+        }
+        let append = self.last_action.as_ref().is_some_and(Action::is_kill);
+        match (append, self.kill_ring.last_mut()) {
+            (true, Some(last)) if backward => last.insert_str(0, &text),
+            (true, Some(last)) => last.push_str(&text),
+            _ => self.kill_ring.push(text),
+        }
+    }
+
+    pub fn print(&mut self, text: &str) -> io::Result<()> {
+        self.printer.print(&mut self.line, text)
+    }
+
+    /// Prints output that does not fit on the screen through the pager the options ask for.
+    ///
+    /// The output pane of the full-screen layout scrolls by itself and needs no pager.
+    pub fn page(&mut self, text: &str) -> io::Result<()> {
+        let threshold = match self.options.pager_threshold {
+            Some(lines) => lines,
+            // The prompt and the status bar stay on the screen.
+            None => terminal::size().map_or(usize::MAX, |(_, rows)| {
+                (rows as usize).saturating_sub(1 + self.footer().len())
+            }),
+        };
+        if !self.capabilities.interactive
+            || self.options.pager == PagerStyle::Off
+            || self.printer.is_fullscreen()
+            || text.lines().count() <= threshold
+        {
+            return self.print(text);
+        }
+        if self.options.pager == PagerStyle::External {
+            match env::var("PAGER") {
+                Ok(pager) if !pager.trim().is_empty() => return self.external_pager(&pager, text),
+                _ => (),
+            }
+        }
+        Pager::new(text, self.line.theme()).run(&mut self.input, &mut self.printer)?;
+        self.printer.redraw(&mut self.line)
+    }
+
+    /// Pipes the text into a pager command, which gets the terminal until it exits.
+    fn external_pager(&mut self, pager: &str, text: &str) -> io::Result<()> {
+        let mut command = Command::new("/bin/sh");
+        command.arg("-c").arg(pager).stdin(Stdio::piped());
+        self.printer.suspend()?;
+        let result = command.spawn().and_then(|mut child| {
+            if let Some(mut stdin) = child.stdin.take() {
+                // The pager may quit before it read everything.
+                let _ = stdin.write_all(text.as_bytes());
+            }
+            child.wait()
+        });
+        self.printer.resume(&mut self.line)?;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                self.print_error(&format!("Could not run the pager {}: {}", pager, e))?;
+                self.print(text)
+            }
+        }
+    }
+
+    pub fn print_error(&mut self, text: &str) -> io::Result<()> {
+        log::warn!("{}", text);
+        let text = self.line.theme().error.apply(text);
+        self.print(&text)
+    }
+
+    /// The left and the right prompt for the current state.
+    pub fn prompts(&self) -> (Rendered, Rendered) {
+        let context = PromptContext {
+            status: self.last_status,
+            count: self.count,
+            colors: self.capabilities.colors,
+            variables: &self.variables,
+        };
+        let prompt = match &self.argument {
+            Some(argument) => Rendered::plain(&format!("(arg: {}) ", argument.count())),
+            None => context.render(&self.config.prompt),
+        };
+        (prompt, context.render(&self.config.right_prompt))
+    }
+
+    /// The status bar below the line and the inspector when it is open.
+    fn footer(&self) -> Vec<String> {
+        let mode = match self.mode {
+            Mode::Emacs => "emacs",
+            Mode::ViInsert => "vi insert",
+            Mode::ViCommand => "vi command",
+        };
+        let mut status = format!(" {}  col {}", mode, self.line.position.x() + 1);
+        let pending = self.keymaps.pending(self.mode);
+        if !pending.is_empty() {
+            let keys = pending.iter().map(Key::to_string).collect::<Vec<_>>();
+            status += &format!("  {}-", keys.join(" "));
+        }
+        status += &format!("  status {}", self.last_status);
+        if let Some(message) = self.status.message() {
+            status += &format!("  {}", message);
+        }
+        let mut footer = vec![status];
+        footer.extend(self.inspector.rows(&self.line));
+        footer
+    }
+
+    /// Looks up the suggestion for the current line and redraws it.
+    pub fn refresh(&mut self) -> io::Result<()> {
+        let text = self.line.collect();
+        let sources = Sources {
+            history: &self.history,
+            aliases: &self.aliases,
+        };
+        let inline = self.options.completion_style == CompletionStyle::Inline;
+        let suggestion = if text.is_empty() || !inline {
+            None
+        } else {
+            self.suggesters
+                .iter()
+                .find_map(|suggester| suggester.suggest(&text, &sources))
+        };
+        self.line.set_suggestion(suggestion);
+        let (prompt, right_prompt) = self.prompts();
+        self.line.set_prompt(prompt, right_prompt);
+        self.printer.set_footer(self.footer());
+        let start = Instant::now();
+        let result = self.printer.redraw(&mut self.line);
+        self.inspector.set_render_time(start.elapsed());
+        result
+    }
+}
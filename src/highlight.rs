@@ -0,0 +1,91 @@
+use super::my_parser::{self, Token, TokenKind};
+use super::theme::Theme;
+use crossterm::style::{ContentStyle, Stylize};
+
+/// Colors the input line, tokens in front of an edit are kept from the previous call.
+#[derive(Debug)]
+pub struct Highlighter {
+    text: String,
+    tokens: Vec<Token>,
+}
+impl Highlighter {
+    pub fn new() -> Self {
+        Highlighter {
+            text: String::new(),
+            tokens: Vec::new(),
+        }
+    }
+
+    fn update(&mut self, text: &str) {
+        let common = self
+            .text
+            .char_indices()
+            .zip(text.chars())
+            .find(|((_, old), new)| old != new)
+            .map(|((i, _), _)| i)
+            .unwrap_or(self.text.len().min(text.len()));
+
+        // A token that ends right at the edit could be continued by it.
+        let keep = self.tokens.iter().take_while(|t| t.end < common).count();
+        self.tokens.truncate(keep);
+        let from = self.tokens.last().map_or(0, |t| t.end);
+        self.tokens.extend(my_parser::tokenize(text, from));
+        self.text = text.to_string();
+    }
+
+    fn style(kind: &TokenKind, part: &str, theme: &Theme, aliases: &[String]) -> ContentStyle {
+        let known = |name: &str| {
+            my_parser::COMMANDS.contains(&name)
+                || name.starts_with('!')
+                || aliases.iter().any(|alias| alias == name)
+        };
+        match kind {
+            TokenKind::Space => theme.input.content(),
+            TokenKind::Word => theme.argument.content(),
+            TokenKind::Command if known(&part[1..]) => theme.command.content(),
+            TokenKind::Command => theme.error.content(),
+            TokenKind::Flag => theme.flag.content(),
+            TokenKind::Str { terminated: true } => theme.string.content(),
+            TokenKind::Str { terminated: false } => theme.error.content().underlined(),
+            TokenKind::Path => theme.path.content(),
+        }
+    }
+
+    /// The text with ANSI colors, it takes up exactly as many columns as `text`.
+    ///
+    /// Commands are known if they are built in, `:!` or in `aliases`.
+    /// `region` is a byte range that is drawn with the selection style on top of the colors.
+    pub fn highlight(
+        &mut self,
+        text: &str,
+        region: Option<(usize, usize)>,
+        theme: &Theme,
+        aliases: &[String],
+    ) -> String {
+        self.update(text);
+        let mut result = String::new();
+        for token in &self.tokens {
+            let style =
+                Highlighter::style(&token.kind, &text[token.start..token.end], theme, aliases);
+            let mut cuts = vec![token.start, token.end];
+            if let Some((start, end)) = region {
+                cuts.extend(
+                    [start, end]
+                        .iter()
+                        .filter(|&&cut| cut > token.start && cut < token.end),
+                );
+            }
+            cuts.sort_unstable();
+            for cut in cuts.windows(2) {
+                let part = &text[cut[0]..cut[1]];
+                match region {
+                    Some((start, end)) if cut[0] >= start && cut[1] <= end => {
+                        result += &theme.selection.patch(style).apply(part).to_string()
+                    }
+                    _ => result += &style.apply(part).to_string(),
+                }
+            }
+        }
+        result
+    }
+}
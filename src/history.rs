@@ -0,0 +1,38 @@
+/// Lines that were entered, oldest first.
+pub struct History {
+    entries: Vec<String>,
+    /// The oldest entries are dropped beyond this.
+    max: usize,
+}
+impl History {
+    pub fn new() -> Self {
+        History {
+            entries: Vec::new(),
+            max: usize::MAX,
+        }
+    }
+
+    pub fn set_max(&mut self, max: usize) {
+        self.max = max;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        if self.entries.len() > self.max {
+            self.entries.drain(..self.entries.len() - self.max);
+        }
+    }
+
+    /// Blank lines and repeats of the previous entry are not stored.
+    pub fn push(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.entries.last().map(String::as_str) == Some(entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        self.trim();
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+}
@@ -0,0 +1,61 @@
+use super::currentline::CurrentLine;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Events kept for the inspector, the oldest are dropped first.
+const EVENTS: usize = 6;
+
+/// Panel below the status bar with the recent events and the state of the line, toggled with F12.
+#[derive(Default)]
+pub struct Inspector {
+    pub visible: bool,
+    /// Raw events with what the keymap made of them, the most recent last.
+    events: VecDeque<(String, String)>,
+    /// Time the last redraw of the line took.
+    render: Duration,
+}
+impl Inspector {
+    /// Keeps an event even while the panel is hidden, it shows the history when it opens.
+    pub fn record(&mut self, event: String, resolved: String) {
+        if self.events.len() == EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back((event, resolved));
+    }
+
+    pub fn set_render_time(&mut self, render: Duration) {
+        self.render = render;
+    }
+
+    /// The rows of the panel, empty while it is hidden.
+    pub fn rows(&self, line: &CurrentLine) -> Vec<String> {
+        if !self.visible {
+            return Vec::new();
+        }
+        let mut rows = vec![
+            format!(
+                " left {:?}  right {:?}  cursor {}  mark {}  undo {}",
+                line.left(),
+                line.right(),
+                line.position.x(),
+                line.mark()
+                    .map_or(String::from("-"), |mark| mark.to_string()),
+                line.undo_depth()
+            ),
+            format!(
+                " render {:.2}ms  events {}/{}  F12 to close",
+                self.render.as_secs_f64() * 1000.0,
+                self.events.len(),
+                EVENTS
+            ),
+        ];
+        rows.extend(
+            self.events
+                .iter()
+                .map(|(event, resolved)| format!(" {:<28} {}", resolved, event)),
+        );
+        // The panel keeps its height, the line does not move while events come in.
+        rows.resize(2 + EVENTS, String::new());
+        rows
+    }
+}
@@ -0,0 +1,518 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Everything a key can be bound to, also the unit keyboard macros are recorded in.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    SelfInsert(char),
+    /// Pasted text, inserted as one edit.
+    InsertText(String),
+    BackwardChar,
+    ForwardChar,
+    BackwardWord,
+    ForwardWord,
+    BeginningOfLine,
+    EndOfLine,
+    BackwardDeleteChar,
+    DeleteChar,
+    KillLine,
+    KillWord,
+    UnixLineDiscard,
+    UnixWordRubout,
+    BackwardKillWord,
+    Yank,
+    TransposeChars,
+    TransposeWords,
+    UpcaseWord,
+    DowncaseWord,
+    CapitalizeWord,
+    SetMark,
+    KillRegion,
+    CopyRegionAsKill,
+    ExchangePointAndMark,
+    Undo,
+    OverwriteMode,
+    ClearScreen,
+    AcceptLine,
+    Interrupt,
+    StartMacro,
+    EndMacro,
+    CallLastMacro,
+    CallMacro(String),
+    DigitArgument(u8),
+    NegativeArgument,
+    UniversalArgument,
+    /// Kills the text before and after the cursor, like vi `dd`.
+    KillWholeLine,
+    ViCommandMode,
+    ViInsertMode,
+    /// Enters insert mode after the character under the cursor.
+    ViAppend,
+    ViAppendEol,
+    ViInsertBol,
+    /// Vi `0`, part of a count that is being typed, otherwise beginning-of-line.
+    ViZero,
+    /// Scrolls the output pane of the full-screen layout by a page.
+    ScrollUp,
+    ScrollDown,
+    /// Shows or hides the panel with recent events and the state of the line.
+    ToggleInspector,
+}
+
+impl Action {
+    /// The action a negative numeric argument turns this one into.
+    pub fn reverse(&self) -> Option<Action> {
+        let reverse = match self {
+            Action::BackwardChar => Action::ForwardChar,
+            Action::ForwardChar => Action::BackwardChar,
+            Action::BackwardWord => Action::ForwardWord,
+            Action::ForwardWord => Action::BackwardWord,
+            Action::BackwardDeleteChar => Action::DeleteChar,
+            Action::DeleteChar => Action::BackwardDeleteChar,
+            Action::KillWord => Action::BackwardKillWord,
+            Action::BackwardKillWord => Action::KillWord,
+            Action::KillLine => Action::UnixLineDiscard,
+            Action::UnixLineDiscard => Action::KillLine,
+            Action::ScrollUp => Action::ScrollDown,
+            Action::ScrollDown => Action::ScrollUp,
+            _ => return None,
+        };
+        Some(reverse)
+    }
+
+    /// Kills that follow each other are collected into one kill buffer entry.
+    pub fn is_kill(&self) -> bool {
+        matches!(
+            self,
+            Action::KillLine
+                | Action::KillWholeLine
+                | Action::KillWord
+                | Action::UnixLineDiscard
+                | Action::UnixWordRubout
+                | Action::BackwardKillWord
+                | Action::KillRegion
+        )
+    }
+}
+
+/// A key press without its kind and state, `SHIFT` is dropped for characters.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+impl Key {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers = modifiers;
+        if let KeyCode::Char(_) = code {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        Key { code, modifiers }
+    }
+
+    /// Parses emacs notation like `C-x`, `M-f`, `C-M-a`, `Enter` or `F5`.
+    pub fn parse(description: &str) -> Option<Key> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = description;
+        let prefixes = [
+            ("C-", KeyModifiers::CONTROL),
+            ("M-", KeyModifiers::ALT),
+            ("S-", KeyModifiers::SHIFT),
+        ];
+        // A modifier needs a key after it, `C-` alone is not a key.
+        while let Some((after, modifier)) = prefixes
+            .iter()
+            .find_map(|(prefix, modifier)| Some((rest.strip_prefix(prefix)?, *modifier)))
+            .filter(|(after, _)| !after.is_empty())
+        {
+            modifiers.insert(modifier);
+            rest = after;
+        }
+
+        let code = match rest {
+            "Enter" | "RET" => KeyCode::Enter,
+            "Tab" | "TAB" => KeyCode::Tab,
+            "BackTab" => KeyCode::BackTab,
+            "Backspace" | "DEL" => KeyCode::Backspace,
+            "Delete" => KeyCode::Delete,
+            "Insert" => KeyCode::Insert,
+            "Esc" | "ESC" => KeyCode::Esc,
+            "Space" | "SPC" => KeyCode::Char(' '),
+            "Left" => KeyCode::Left,
+            "Right" => KeyCode::Right,
+            "Up" => KeyCode::Up,
+            "Down" => KeyCode::Down,
+            "Home" => KeyCode::Home,
+            "End" => KeyCode::End,
+            "PageUp" => KeyCode::PageUp,
+            "PageDown" => KeyCode::PageDown,
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => KeyCode::Char(c),
+                    (Some('F'), Some(_)) => KeyCode::F(rest[1..].parse().ok()?),
+                    _ => return None,
+                }
+            }
+        };
+        Some(Key::new(code, modifiers))
+    }
+
+    /// Parses a space separated chord like `C-x (`.
+    pub fn parse_sequence(description: &str) -> Option<Vec<Key>> {
+        let keys = description
+            .split_whitespace()
+            .map(Key::parse)
+            .collect::<Option<Vec<_>>>()?;
+        if keys.is_empty() {
+            None
+        } else {
+            Some(keys)
+        }
+    }
+}
+impl From<&KeyEvent> for Key {
+    fn from(event: &KeyEvent) -> Self {
+        Key::new(event.code, event.modifiers)
+    }
+}
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "M-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{}", c),
+            KeyCode::F(n) => write!(f, "F{}", n),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Esc => write!(f, "Esc"),
+            code => write!(f, "{:?}", code),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Resolved {
+    Action(Action),
+    /// The keys so far are the start of a longer binding.
+    Pending,
+    Unbound(Vec<Key>),
+}
+
+/// Which keymap resolves the keys.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Emacs,
+    ViInsert,
+    ViCommand,
+}
+
+pub struct Keymap {
+    bindings: HashMap<Vec<Key>, Action>,
+    pending: Vec<Key>,
+    /// Unbound plain characters are inserted, not in vi command mode.
+    self_insert: bool,
+}
+impl Keymap {
+    fn with(bindings: Vec<(&str, Action)>, self_insert: bool) -> Self {
+        let mut keymap = Keymap {
+            bindings: HashMap::new(),
+            pending: Vec::new(),
+            self_insert,
+        };
+        for (keys, action) in bindings {
+            if let Some(keys) = Key::parse_sequence(keys) {
+                keymap.bind(keys, action);
+            }
+        }
+        keymap
+    }
+
+    pub fn emacs() -> Self {
+        let mut keymap = Keymap::with(
+            vec![
+                ("C-a", Action::BeginningOfLine),
+                ("C-e", Action::EndOfLine),
+                ("C-b", Action::BackwardChar),
+                ("C-f", Action::ForwardChar),
+                ("Left", Action::BackwardChar),
+                ("Right", Action::ForwardChar),
+                ("Home", Action::BeginningOfLine),
+                ("End", Action::EndOfLine),
+                ("M-b", Action::BackwardWord),
+                ("M-f", Action::ForwardWord),
+                ("C-Left", Action::BackwardWord),
+                ("C-Right", Action::ForwardWord),
+                ("Backspace", Action::BackwardDeleteChar),
+                ("C-d", Action::DeleteChar),
+                ("Delete", Action::DeleteChar),
+                ("Insert", Action::OverwriteMode),
+                ("C-k", Action::KillLine),
+                ("M-d", Action::KillWord),
+                ("C-u", Action::UnixLineDiscard),
+                ("C-w", Action::KillRegion),
+                ("M-Backspace", Action::BackwardKillWord),
+                ("C-y", Action::Yank),
+                ("C-t", Action::TransposeChars),
+                ("M-t", Action::TransposeWords),
+                ("M-u", Action::UpcaseWord),
+                ("M-l", Action::DowncaseWord),
+                ("M-c", Action::CapitalizeWord),
+                ("C-Space", Action::SetMark),
+                ("M-w", Action::CopyRegionAsKill),
+                ("C-x C-x", Action::ExchangePointAndMark),
+                ("C-_", Action::Undo),
+                ("C-7", Action::Undo),
+                ("C-x u", Action::Undo),
+                ("C-l", Action::ClearScreen),
+                ("Enter", Action::AcceptLine),
+                ("C-c", Action::Interrupt),
+                ("C-x (", Action::StartMacro),
+                ("C-x )", Action::EndMacro),
+                ("C-x e", Action::CallLastMacro),
+                ("PageUp", Action::ScrollUp),
+                ("PageDown", Action::ScrollDown),
+                ("F12", Action::ToggleInspector),
+            ],
+            true,
+        );
+        keymap.bind(
+            vec![Key::new(KeyCode::Char('-'), KeyModifiers::ALT)],
+            Action::NegativeArgument,
+        );
+        for digit in 0..=9 {
+            let c = char::from(b'0' + digit);
+            let key = Key::new(KeyCode::Char(c), KeyModifiers::ALT);
+            keymap.bind(vec![key], Action::DigitArgument(digit));
+        }
+        keymap
+    }
+
+    /// The few editing keys that work while typing in vi mode.
+    pub fn vi_insert() -> Self {
+        Keymap::with(
+            vec![
+                ("Esc", Action::ViCommandMode),
+                ("Left", Action::BackwardChar),
+                ("Right", Action::ForwardChar),
+                ("Home", Action::BeginningOfLine),
+                ("End", Action::EndOfLine),
+                ("Backspace", Action::BackwardDeleteChar),
+                ("Delete", Action::DeleteChar),
+                ("C-w", Action::UnixWordRubout),
+                ("C-u", Action::UnixLineDiscard),
+                ("C-l", Action::ClearScreen),
+                ("Enter", Action::AcceptLine),
+                ("C-c", Action::Interrupt),
+                ("PageUp", Action::ScrollUp),
+                ("PageDown", Action::ScrollDown),
+                ("F12", Action::ToggleInspector),
+            ],
+            true,
+        )
+    }
+
+    pub fn vi_command() -> Self {
+        let mut keymap = Keymap::with(
+            vec![
+                ("i", Action::ViInsertMode),
+                ("a", Action::ViAppend),
+                ("A", Action::ViAppendEol),
+                ("I", Action::ViInsertBol),
+                ("h", Action::BackwardChar),
+                ("l", Action::ForwardChar),
+                ("Space", Action::ForwardChar),
+                ("Backspace", Action::BackwardChar),
+                ("Left", Action::BackwardChar),
+                ("Right", Action::ForwardChar),
+                ("w", Action::ForwardWord),
+                ("b", Action::BackwardWord),
+                ("0", Action::ViZero),
+                ("^", Action::BeginningOfLine),
+                ("$", Action::EndOfLine),
+                ("Home", Action::BeginningOfLine),
+                ("End", Action::EndOfLine),
+                ("x", Action::DeleteChar),
+                ("Delete", Action::DeleteChar),
+                ("X", Action::BackwardDeleteChar),
+                ("D", Action::KillLine),
+                ("d d", Action::KillWholeLine),
+                ("d w", Action::KillWord),
+                ("d b", Action::BackwardKillWord),
+                ("d $", Action::KillLine),
+                ("d 0", Action::UnixLineDiscard),
+                ("p", Action::Yank),
+                ("u", Action::Undo),
+                ("C-l", Action::ClearScreen),
+                ("Enter", Action::AcceptLine),
+                ("C-c", Action::Interrupt),
+                ("PageUp", Action::ScrollUp),
+                ("PageDown", Action::ScrollDown),
+                ("F12", Action::ToggleInspector),
+            ],
+            false,
+        );
+        for digit in 1..=9 {
+            let key = Key::new(KeyCode::Char(char::from(b'0' + digit)), KeyModifiers::NONE);
+            keymap.bind(vec![key], Action::DigitArgument(digit));
+        }
+        keymap
+    }
+
+    pub fn bind(&mut self, keys: Vec<Key>, action: Action) {
+        self.bindings.insert(keys, action);
+    }
+
+    /// Keys of a chord that is not complete yet.
+    pub fn pending(&self) -> &[Key] {
+        &self.pending
+    }
+
+    pub fn resolve(&mut self, key: Key) -> Resolved {
+        self.pending.push(key);
+        if let Some(action) = self.bindings.get(&self.pending) {
+            self.pending.clear();
+            return Resolved::Action(action.clone());
+        }
+        if self
+            .bindings
+            .keys()
+            .any(|keys| keys.starts_with(&self.pending))
+        {
+            return Resolved::Pending;
+        }
+
+        let pending = std::mem::take(&mut self.pending);
+        match key {
+            Key {
+                code: KeyCode::Char(c),
+                modifiers: KeyModifiers::NONE,
+            } if pending.len() == 1 && self.self_insert => Resolved::Action(Action::SelfInsert(c)),
+            _ => Resolved::Unbound(pending),
+        }
+    }
+}
+
+/// A keymap for every mode, the editing mode picks which ones are used.
+pub struct Keymaps {
+    emacs: Keymap,
+    vi_insert: Keymap,
+    vi_command: Keymap,
+}
+impl Keymaps {
+    pub fn new() -> Self {
+        Keymaps {
+            emacs: Keymap::emacs(),
+            vi_insert: Keymap::vi_insert(),
+            vi_command: Keymap::vi_command(),
+        }
+    }
+
+    pub fn get(&mut self, mode: Mode) -> &mut Keymap {
+        match mode {
+            Mode::Emacs => &mut self.emacs,
+            Mode::ViInsert => &mut self.vi_insert,
+            Mode::ViCommand => &mut self.vi_command,
+        }
+    }
+
+    pub fn pending(&self, mode: Mode) -> &[Key] {
+        match mode {
+            Mode::Emacs => self.emacs.pending(),
+            Mode::ViInsert => self.vi_insert.pending(),
+            Mode::ViCommand => self.vi_command.pending(),
+        }
+    }
+
+    /// Binds the keys in every mode.
+    pub fn bind(&mut self, keys: Vec<Key>, action: Action) {
+        for keymap in [&mut self.emacs, &mut self.vi_insert, &mut self.vi_command] {
+            keymap.bind(keys.clone(), action.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> Option<Key> {
+        Some(Key::new(code, modifiers))
+    }
+
+    #[test]
+    fn parse_reads_modifiers() {
+        let control = KeyModifiers::CONTROL;
+        assert_eq!(Key::parse("C-x"), key(KeyCode::Char('x'), control));
+        assert_eq!(
+            Key::parse("C-M-a"),
+            key(KeyCode::Char('a'), control | KeyModifiers::ALT)
+        );
+        assert_eq!(
+            Key::parse("M--"),
+            key(KeyCode::Char('-'), KeyModifiers::ALT)
+        );
+        // Characters are the same with and without shift.
+        assert_eq!(
+            Key::parse("S-a"),
+            key(KeyCode::Char('a'), KeyModifiers::NONE)
+        );
+        assert_eq!(
+            Key::parse("S-Left"),
+            key(KeyCode::Left, KeyModifiers::SHIFT)
+        );
+    }
+
+    #[test]
+    fn parse_reads_names() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(Key::parse("RET"), key(KeyCode::Enter, none));
+        assert_eq!(Key::parse("SPC"), key(KeyCode::Char(' '), none));
+        assert_eq!(Key::parse("F12"), key(KeyCode::F(12), none));
+        assert_eq!(Key::parse("F"), key(KeyCode::Char('F'), none));
+        assert_eq!(Key::parse("-"), key(KeyCode::Char('-'), none));
+    }
+
+    #[test]
+    fn parse_reads_non_ascii_characters() {
+        let none = KeyModifiers::NONE;
+        assert_eq!(Key::parse("€"), key(KeyCode::Char('€'), none));
+        assert_eq!(Key::parse("日"), key(KeyCode::Char('日'), none));
+        assert_eq!(
+            Key::parse("C-€"),
+            key(KeyCode::Char('€'), KeyModifiers::CONTROL)
+        );
+        assert_eq!(Key::parse("€€"), None);
+    }
+
+    #[test]
+    fn parse_rejects_unknown_keys() {
+        assert_eq!(Key::parse(""), None);
+        assert_eq!(Key::parse("C-"), None);
+        assert_eq!(Key::parse("Fx"), None);
+        assert_eq!(Key::parse("C-foo"), None);
+        assert_eq!(Key::parse_sequence("  "), None);
+        assert_eq!(Key::parse_sequence("C-x C-foo"), None);
+    }
+
+    #[test]
+    fn parse_sequence_reads_chords() {
+        assert_eq!(
+            Key::parse_sequence("C-x  ("),
+            Some(vec![
+                Key::new(KeyCode::Char('x'), KeyModifiers::CONTROL),
+                Key::new(KeyCode::Char('('), KeyModifiers::NONE)
+            ])
+        );
+    }
+}
@@ -1,355 +1,815 @@
-use super::currentline::CurrentLine;
-use super::debug::{debug_event, debug_line, debug_message};
-use super::my_parser;
+use super::capabilities::Capabilities;
+use super::config::Config;
+use super::currentline::{Case, CurrentLine};
+use super::editor::{Argument, Editor};
+use super::keymap::{Action, Key, Mode, Resolved};
+use super::macros::MAX_DEPTH;
+use super::my_parser::{self, AliasCommand, Expansion, MacroCommand, OptionCommand};
+use super::options::{Options, Source};
+use super::printer::Printer;
+use super::recording::{EventSource, Input, Recorder, POLL_INTERVAL};
+use super::theme::Theme;
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
-    terminal::{self, ClearType},
-    ExecutableCommand,
+    event::{Event, KeyCode, KeyEventKind, MouseEventKind},
+    terminal, ExecutableCommand,
 };
+use std::env;
+use std::fs;
 use std::io::{self, Write};
+use std::process::{self, Stdio};
+use std::thread;
+use std::time::Duration;
 
-// REGULAR CHARS
-fn regular_character(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char(c),
-        kind: KeyEventKind::Press,
-        modifiers: KeyModifiers::NONE | KeyModifiers::SHIFT,
-        ..
-    }) = event
-    {
-        line.add_char(c.clone())?;
-        debug_line(line)?;
-        debug_event(event)?;
-    }
+/// Deepest nesting of scripts loading scripts.
+const MAX_SCRIPT_DEPTH: usize = 8;
+
+/// Spaces a pasted tab turns into.
+const TAB_WIDTH: usize = 4;
+
+/// Status of a command that worked.
+const SUCCESS: i32 = 0;
+const FAILURE: i32 = 1;
 
+fn accept_line(editor: &mut Editor) -> io::Result<()> {
+    let line = editor.line.collect();
+    editor.history.push(&line);
+    if !line.trim().is_empty() {
+        editor.count += 1;
+    }
+    editor.printer.submit(&mut editor.line)?;
+    if editor.mode == Mode::ViCommand {
+        editor.mode = Mode::ViInsert;
+    }
+    editor.last_status = run_line(editor, &line)?;
     Ok(())
 }
 
-// CTRL
-fn control_l(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('l'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        line.set_position_start_x();
-        line.set_position_start_y();
-        line.clear();
-
-        io::stdout().execute(cursor::MoveTo(0, 0))?;
-        io::stdout().execute(terminal::Clear(ClearType::All))?;
-        print!("\r> ");
-        io::stdout().flush()?;
-        line.set_position_start_x();
-        line.set_position_start_y();
-        debug_line(line)?;
-        debug_event(event)?;
+/// Prints the error and returns the failure status.
+fn fail(editor: &mut Editor, message: &str) -> io::Result<i32> {
+    editor.print_error(message)?;
+    Ok(FAILURE)
+}
+
+/// Runs the commands of a line typed at the prompt or read from a script.
+///
+/// The status is the one of the last command that ran.
+fn run_line(editor: &mut Editor, line: &str) -> io::Result<i32> {
+    run_chain(editor, my_parser::split_chain(line))
+}
+
+/// Runs chained commands, each one depending on the status of the one before.
+fn run_chain(editor: &mut Editor, commands: Vec<(my_parser::Chain, String)>) -> io::Result<i32> {
+    let mut status = SUCCESS;
+    for (chain, command) in commands {
+        let run = match chain {
+            my_parser::Chain::Always => true,
+            my_parser::Chain::OnSuccess => status == SUCCESS,
+            my_parser::Chain::OnFailure => status != SUCCESS,
+        };
+        if run {
+            log::debug!("running {:?}", command);
+            status = run_command(editor, &command)?;
+            log::debug!("{:?} returned status {}", command, status);
+        }
     }
+    Ok(status)
+}
 
-    Ok(())
+/// Runs a single command after expanding aliases and filling in the variables.
+///
+/// The body of an alias is chained like a line at the prompt, the arguments only fill in its commands.
+fn run_command(editor: &mut Editor, line: &str) -> io::Result<i32> {
+    match my_parser::expand_alias(line, &editor.aliases, &editor.expanding) {
+        Expansion::None => (),
+        Expansion::Alias(name, commands) => {
+            editor.expanding.push(name);
+            let result = run_chain(editor, commands);
+            editor.expanding.pop();
+            return result;
+        }
+        Expansion::Loop(names) => {
+            return fail(editor, &format!("Alias loop: {}", names.join(" -> ")));
+        }
+    }
+    // The shell expands its own `$`, the variables are in its environment instead.
+    let line = if my_parser::interpolates(line) {
+        match my_parser::interpolate(line, &editor.variables) {
+            Ok(line) => line,
+            Err(e) => return fail(editor, &e.to_string()),
+        }
+    } else {
+        line.to_string()
+    };
+
+    match my_parser::parse(line) {
+        my_parser::Command::Help => {
+            editor.page(my_parser::HELP)?;
+        }
+        my_parser::Command::Quit => {
+            return Err(io::Error::from(io::ErrorKind::Interrupted));
+        }
+        my_parser::Command::Invalid => {
+            return fail(editor, "Could not parse");
+        }
+        my_parser::Command::Unknown(command) => {
+            return fail(editor, &format!("Unknown command {}", command));
+        }
+        my_parser::Command::Timer(seconds) => {
+            start_timer(&editor.printer, seconds);
+        }
+        my_parser::Command::Macro(command) => {
+            return macro_command(editor, command);
+        }
+        my_parser::Command::Script(path) => {
+            return run_script(editor, &path);
+        }
+        my_parser::Command::Set(name, value) => {
+            editor.variables.insert(name, value);
+        }
+        my_parser::Command::Unset(name) => {
+            if editor.variables.remove(&name).is_none() {
+                return fail(editor, &format!("Undefined variable {}", name));
+            }
+        }
+        my_parser::Command::Alias(command) => {
+            return alias_command(editor, command);
+        }
+        my_parser::Command::Option(command) => {
+            return option_command(editor, command);
+        }
+        my_parser::Command::Shell { command, capture } => {
+            return run_shell(editor, &command, capture);
+        }
+        my_parser::Command::Vars => {
+            let vars = editor
+                .variables
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect::<Vec<_>>();
+            if vars.is_empty() {
+                editor.print("No variables")?;
+            } else {
+                editor.page(&vars.join("\n"))?;
+            }
+        }
+    }
+    Ok(SUCCESS)
 }
-fn control_k(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('k'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        io::stdout().execute(terminal::Clear(ClearType::UntilNewLine))?;
-        line.push_rightbuffer_to_history();
-        line.clear_rightbuffer();
-        line.display()?;
-        debug_line(line)?;
-        debug_event(event)?;
+
+fn option_command(editor: &mut Editor, command: OptionCommand) -> io::Result<i32> {
+    let describe = |options: &Options, name: &str| {
+        options
+            .get(name)
+            .map(|value| format!("{} = {} ({})", name, value, options.source(name)))
+    };
+    let result = match command {
+        OptionCommand::List => {
+            let options = Options::NAMES
+                .iter()
+                .filter_map(|name| describe(&editor.options, name))
+                .collect::<Vec<_>>();
+            editor.page(&options.join("\n"))?;
+            return Ok(SUCCESS);
+        }
+        OptionCommand::Show(name) => match describe(&editor.options, &name) {
+            Some(option) => {
+                editor.print(&option)?;
+                return Ok(SUCCESS);
+            }
+            None => Err(format!("No option named {}", name)),
+        },
+        OptionCommand::Set(name, value) => editor
+            .options
+            .set(&name, &value, Source::Runtime)
+            .map_err(|e| format!("Cannot set {} to {}: {}", name, value, e)),
+        OptionCommand::Reset(name) => editor
+            .options
+            .reset(&name, &editor.config)
+            .map_err(|e| format!("Cannot reset {}: {}", name, e)),
+    };
+    match result {
+        Ok(()) => {
+            editor.apply_options()?;
+            Ok(SUCCESS)
+        }
+        Err(e) => fail(editor, &e),
     }
+}
 
-    Ok(())
+fn alias_command(editor: &mut Editor, command: AliasCommand) -> io::Result<i32> {
+    match command {
+        AliasCommand::List => {
+            let aliases = editor
+                .aliases
+                .iter()
+                .map(|(name, body)| format!("{} = {}", name, body))
+                .collect::<Vec<_>>();
+            if aliases.is_empty() {
+                editor.print("No aliases")?;
+            } else {
+                editor.page(&aliases.join("\n"))?;
+            }
+        }
+        AliasCommand::Define(name, body) => {
+            editor.aliases.insert(name, body);
+            editor.sync_aliases();
+        }
+        AliasCommand::Remove(name) => {
+            if editor.aliases.remove(&name).is_none() {
+                return fail(editor, &format!("No alias named {}", name));
+            }
+            editor.sync_aliases();
+        }
+    }
+    Ok(SUCCESS)
 }
-fn control_a(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('a'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        io::stdout().execute(cursor::MoveTo(2, line.position.y()))?;
-        line.set_position_start_x();
-        debug_line(line)?;
-        debug_event(event)?;
+
+/// Runs every line of a file, blank lines and lines starting with `#` are skipped.
+///
+/// The status is the one of the last line.
+fn run_script(editor: &mut Editor, path: &str) -> io::Result<i32> {
+    if editor.script_depth >= MAX_SCRIPT_DEPTH {
+        return fail(editor, "Scripts are nested too deep");
     }
+    let expanded = match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    };
+    let script = match fs::read_to_string(expanded) {
+        Ok(script) => script,
+        Err(e) => return fail(editor, &format!("Could not load {}: {}", path, e)),
+    };
 
-    Ok(())
+    log::info!("running the script {}", path);
+    editor.script_depth += 1;
+    let mut result = Ok(SUCCESS);
+    for line in script.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        result = run_line(editor, line);
+        if result.is_err() {
+            break;
+        }
+    }
+    editor.script_depth -= 1;
+    result
 }
-fn control_e(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('e'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        line.set_position_end();
-        io::stdout().execute(cursor::MoveTo(2 + line.length() as u16, line.position.y()))?;
-        debug_line(line)?;
-        debug_event(event)?;
+
+/// Runs a command line with `$SHELL -c` on the terminal and returns its exit status.
+///
+/// Captured output is inserted at the cursor, with its lines joined by spaces.
+fn run_shell(editor: &mut Editor, command: &str, capture: bool) -> io::Result<i32> {
+    let shell = env::var("SHELL").unwrap_or_else(|_| String::from("/bin/sh"));
+    let mut child = process::Command::new(&shell);
+    child.arg("-c").arg(command).envs(&editor.variables);
+    if capture {
+        child.stdout(Stdio::piped());
     }
 
-    Ok(())
+    log::info!("running {:?} with {}", command, shell);
+    editor.printer.suspend()?;
+    let result = child.spawn().and_then(|child| child.wait_with_output());
+    if editor.printer.is_fullscreen() && !capture {
+        // The output is on the normal screen, which the layout covers up again.
+        print!("\nPress Enter to return");
+        io::stdout().flush()?;
+        wait_for_enter(editor)?;
+    }
+    editor.printer.resume(&mut editor.line)?;
+
+    let output = match result {
+        Ok(output) => output,
+        Err(e) => return fail(editor, &format!("Could not run {}: {}", shell, e)),
+    };
+    if capture {
+        let text = String::from_utf8_lossy(&output.stdout);
+        let text = text.trim_end_matches('\n').replace('\n', " ");
+        editor.line.insert_str(&text);
+    }
+    // Killed by a signal.
+    Ok(output.status.code().unwrap_or(FAILURE))
 }
-fn control_b(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('b'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        match cursor::position() {
-            Ok((x, y)) => {
-                if x > 2 {
-                    io::stdout().execute(cursor::MoveTo(x - 1, y))?;
-                    io::stdout().execute(terminal::Clear(ClearType::CurrentLine))?;
-                    print!("\r> ");
-                    io::stdout().flush()?;
-                    match line.move_left() {
-                        false => debug_message("Cannot move left")?,
-                        true => (),
-                    }
-                    line.display()?;
-                    debug_line(line)?;
-                    debug_event(event)?;
-                }
+
+/// Waits for Enter, the key goes through the input like every other one.
+fn wait_for_enter(editor: &mut Editor) -> io::Result<()> {
+    terminal::enable_raw_mode()?;
+    loop {
+        if let Some(Event::Key(key)) = editor.input.next(POLL_INTERVAL)? {
+            if key.code == KeyCode::Enter && key.kind != KeyEventKind::Release {
+                return Ok(());
             }
-            _ => (),
         }
     }
-    Ok(())
 }
-fn control_f(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('f'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        match cursor::position() {
-            Ok((x, y)) => {
-                if x > 2 {
-                    io::stdout().execute(cursor::MoveTo(x + 1, y))?;
-                    io::stdout().execute(terminal::Clear(ClearType::CurrentLine))?;
-                    print!("\r> ");
-                    io::stdout().flush()?;
-                    match line.move_right() {
-                        true => (),
-                        false => debug_message("could not move right")?,
-                    }
-                    line.display()?;
-                    debug_line(line)?;
-                    debug_event(event)?;
-                }
+
+fn start_timer(printer: &Printer, seconds: u64) {
+    let external = printer.external();
+    thread::spawn(move || {
+        for left in (1..=seconds).rev() {
+            if !external.print(format!("timer: {}s left", left)) {
+                return;
             }
-            _ => (),
+            thread::sleep(Duration::from_secs(1));
         }
-    }
-    Ok(())
+        external.print("timer: done");
+    });
 }
 
-fn parse_line(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Enter,
-        kind: KeyEventKind::Press,
-        ..
-    }) = event
-    {
-        let mut parse_result = String::from("");
-
-        match my_parser::parse(line.collect()) {
-            my_parser::Command::Help => {
-                my_parser::print_help()?;
-                debug_message("Help")?;
-            }
-            my_parser::Command::Quit => {
-                debug_message("Quit")?;
-                return Err(io::Error::from(io::ErrorKind::Interrupted));
-            }
-            my_parser::Command::Invalid => {
-                debug_message("Invalid")?;
-                parse_result = String::from("Could not parse\n");
-            }
-            my_parser::Command::Load(file) => {
-                debug_message(file.as_str())?;
-                if parse_result.trim() == "" {
-                    parse_result = format!("{}\n", file);
-                }
+// MACROS
+fn call_macro(editor: &mut Editor, actions: Vec<Action>, count: usize) -> io::Result<()> {
+    if editor.macros.depth >= MAX_DEPTH {
+        return ding(editor, "Macros nested too deep");
+    }
+    editor.macros.depth += 1;
+    let mut result = Ok(());
+    'repeat: for _ in 0..count {
+        for action in &actions {
+            result = run_action(editor, action);
+            if result.is_err() {
+                break 'repeat;
             }
-            my_parser::Command::Command(_command) => {
-                parse_result = String::from("Could not parse\n");
+        }
+    }
+    editor.macros.depth -= 1;
+    result
+}
+fn macro_command(editor: &mut Editor, command: MacroCommand) -> io::Result<i32> {
+    match command {
+        MacroCommand::List => {
+            let names = editor
+                .macros
+                .names()
+                .map(|(name, length)| format!("{} ({} actions)", name, length))
+                .collect::<Vec<_>>();
+            if names.is_empty() {
+                editor.print("No named macros")?;
+            } else {
+                editor.page(&names.join("\n"))?;
             }
         }
-        print!("\n\r{}\r> ", parse_result);
+        MacroCommand::Name(name) => {
+            if !editor.macros.name_last(&name) {
+                return fail(editor, "No keyboard macro defined");
+            }
+            editor.print(&format!("Named the last macro {}", name))?;
+        }
+        MacroCommand::Call(name, count) => match editor.macros.get(&name) {
+            Some(actions) => call_macro(editor, actions, count)?,
+            None => return fail(editor, &format!("No macro named {}", name)),
+        },
+        MacroCommand::Bind(name, chord) => {
+            match (editor.macros.get(&name), Key::parse_sequence(&chord)) {
+                (None, _) => return fail(editor, &format!("No macro named {}", name)),
+                (_, None) => return fail(editor, &format!("Cannot parse key {}", chord)),
+                (Some(_), Some(keys)) => {
+                    let action = Action::CallMacro(name.clone());
+                    editor.keymaps.bind(keys, action.clone());
+                    editor.config.bindings.insert(chord.clone(), action);
+                    editor.print(&format!("Bound {} to macro {}", chord, name))?;
+                }
+            }
+        }
+        MacroCommand::Save(name) => match editor.macros.get(&name) {
+            Some(actions) => {
+                editor.config.macros.insert(name.clone(), actions);
+                if let Err(e) = editor.config.save() {
+                    return fail(editor, &format!("Could not save the config: {}", e));
+                }
+                editor.print(&format!("Saved macro {} to the config", name))?;
+            }
+            None => return fail(editor, &format!("No macro named {}", name)),
+        },
+    }
+    Ok(SUCCESS)
+}
 
-        line.clear();
-        line.position_down();
-        line.position_down();
-        line.set_position_start_x();
-        io::stdout().execute(cursor::MoveTo(2, line.position.y()))?;
-        debug_line(line)?;
-        debug_event(event)?;
-        io::stdout().flush()?;
+/// Handles `M-<digit>`, `M--` and `C-u`, returns false for every other action.
+fn argument_action(editor: &mut Editor, action: &Action) -> bool {
+    let starts = matches!(
+        action,
+        Action::DigitArgument(_) | Action::NegativeArgument | Action::UniversalArgument
+    );
+    if editor.argument.is_none() && !starts {
+        return false;
     }
-    Ok(())
+    let argument = editor.argument.get_or_insert_with(Argument::default);
+    match action {
+        Action::DigitArgument(digit) => argument.push_digit(*digit),
+        Action::NegativeArgument => argument.negate(),
+        Action::UniversalArgument => argument.multiply(),
+        // Once an argument is started plain digits and `-` continue it.
+        Action::SelfInsert(c) if c.is_ascii_digit() => {
+            argument.push_digit(c.to_digit(10).unwrap_or(0) as u8)
+        }
+        Action::SelfInsert('-') if !argument.has_digits() => argument.negate(),
+        Action::ViZero => argument.push_digit(0),
+        _ => return false,
+    }
+    true
 }
-fn backspace(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Backspace,
-        kind: KeyEventKind::Press,
-        ..
-    }) = event
-    {
-        line.delete_left();
-        line.display()?;
-        debug_line(line)?;
-        debug_event(event)?;
+
+fn run_action(editor: &mut Editor, action: &Action) -> io::Result<()> {
+    match action {
+        Action::StartMacro | Action::EndMacro | Action::CallLastMacro | Action::ToggleInspector => {
+        }
+        _ => editor.macros.record(action),
+    }
+    if argument_action(editor, action) {
+        return editor.refresh();
     }
 
-    Ok(())
-}
-fn control_c(event: &Event) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('c'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        return Err(io::Error::from(io::ErrorKind::Interrupted));
+    let count = editor
+        .argument
+        .take()
+        .map_or(1, |argument| argument.count());
+    let action = match (count < 0, action.reverse()) {
+        (true, Some(reverse)) => reverse,
+        _ => action.clone(),
+    };
+    let previous = editor.last_action.clone();
+    let snapshot = editor.line.snapshot();
+    for _ in 0..count.unsigned_abs() {
+        perform(editor, &action)?;
+        editor.last_action = Some(action.clone());
     }
 
-    Ok(())
+    if editor.line.changed_since(&snapshot) {
+        editor.line.clear_mark();
+    }
+    match (&action, previous) {
+        (Action::Undo | Action::AcceptLine | Action::ClearScreen, _) => (),
+        // A run of typed characters is undone at once.
+        (Action::SelfInsert(_), Some(Action::SelfInsert(_))) => (),
+        _ => editor.line.push_undo(snapshot),
+    }
+
+    log::trace!("after {:?}: {:?}", action, editor.line);
+    editor.refresh()
 }
-fn prompt() -> io::Result<()> {
-    print!("\r> ");
-    io::stdout().flush()?;
 
-    Ok(())
+/// Rings the bell for an action that could not do anything and says why.
+fn ding(editor: &mut Editor, message: &str) -> io::Result<()> {
+    log::debug!("ding: {}", message);
+    editor.status.flash(message);
+    editor.bell()
 }
-fn control_d(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('d'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::CONTROL,
-        ..
-    }) = event
-    {
-        line.delete_right();
-        line.display()?;
-        debug_line(line)?;
-        debug_event(event)?;
+
+fn perform(editor: &mut Editor, action: &Action) -> io::Result<()> {
+    let line = &mut editor.line;
+    match action {
+        Action::SelfInsert(c) => line.self_insert(*c),
+        Action::InsertText(text) => line.insert_str(text),
+        Action::BackwardChar => {
+            if !line.move_left() {
+                ding(editor, "Cannot move left")?;
+            }
+        }
+        Action::ForwardChar => {
+            if !line.accept_suggestion(false) && !line.move_right() {
+                ding(editor, "could not move right")?;
+            }
+        }
+        Action::BackwardWord => match line.left_word() {
+            None => ding(editor, "Could not move back word")?,
+            Some(x) => line.set_position_x(x),
+        },
+        Action::ForwardWord => {
+            if !line.accept_suggestion(true) {
+                match line.right_word() {
+                    None => ding(editor, "Could not move forward word")?,
+                    Some(x) => line.set_position_x(x),
+                }
+            }
+        }
+        Action::BeginningOfLine | Action::ViZero => line.set_position_start_x(),
+        Action::EndOfLine => {
+            if !line.accept_suggestion(false) {
+                line.set_position_end();
+            }
+        }
+        Action::BackwardDeleteChar => line.delete_left(),
+        Action::DeleteChar => {
+            if !line.delete_right() {
+                ding(editor, "Right buffer empty")?;
+            }
+        }
+        Action::KillLine => {
+            let killed = line.kill_to_end();
+            editor.kill(killed, false);
+        }
+        Action::KillWord => {
+            let x = line.position.x();
+            let end = line.right_word().unwrap_or(x);
+            let killed = line.kill_range(x, end);
+            editor.kill(killed, false);
+        }
+        Action::UnixLineDiscard => {
+            let killed = line.kill_to_start();
+            editor.kill(killed, true);
+        }
+        Action::UnixWordRubout => {
+            let start = line.unix_word_start();
+            let killed = line.kill_range(start, line.position.x());
+            editor.kill(killed, true);
+        }
+        Action::BackwardKillWord => match line.left_word() {
+            None => ding(editor, "Could not move back word")?,
+            Some(start) => {
+                let killed = line.kill_range(start, line.position.x());
+                editor.kill(killed, true);
+            }
+        },
+        Action::KillWholeLine => {
+            line.set_position_end();
+            let killed = line.kill_to_start();
+            editor.kill(killed, true);
+        }
+        Action::SetMark => {
+            line.set_mark();
+            editor.status.flash("Mark set");
+        }
+        Action::KillRegion => match line.region() {
+            Some((start, end)) => {
+                let backward = line.position.x() == end;
+                let killed = line.kill_range(start, end);
+                line.clear_mark();
+                editor.kill(killed, backward);
+            }
+            // Without a mark C-w keeps working like unix-word-rubout.
+            None => perform(editor, &Action::UnixWordRubout)?,
+        },
+        Action::CopyRegionAsKill => match line.region_text() {
+            Some(text) => {
+                line.clear_mark();
+                editor.kill(text, false);
+            }
+            None => ding(editor, "The mark is not set now")?,
+        },
+        Action::ExchangePointAndMark => {
+            if !line.exchange_point_and_mark() {
+                ding(editor, "No mark set in this buffer")?;
+            }
+        }
+        Action::Yank => match editor.kill_ring.last() {
+            Some(text) => line.insert_str(text),
+            None => ding(editor, "Kill ring is empty")?,
+        },
+        Action::TransposeChars => {
+            if !line.transpose_chars() {
+                ding(editor, "Nothing to transpose")?;
+            }
+        }
+        Action::TransposeWords => {
+            if !line.transpose_words() {
+                ding(editor, "Nothing to transpose")?;
+            }
+        }
+        Action::UpcaseWord | Action::DowncaseWord | Action::CapitalizeWord => {
+            let case = match action {
+                Action::UpcaseWord => Case::Upper,
+                Action::DowncaseWord => Case::Lower,
+                _ => Case::Capital,
+            };
+            if !line.change_case_word(case) {
+                ding(editor, "No word after the cursor")?;
+            }
+        }
+        Action::Undo => {
+            if !line.undo() {
+                ding(editor, "No further undo information")?;
+            }
+        }
+        Action::OverwriteMode => {
+            if line.toggle_overwrite() {
+                io::stdout().execute(cursor::SetCursorStyle::BlinkingUnderScore)?;
+                editor.status.flash("Overwrite mode enabled");
+            } else {
+                io::stdout().execute(cursor::SetCursorStyle::BlinkingBlock)?;
+                editor.status.flash("Overwrite mode disabled");
+            }
+        }
+        Action::ClearScreen => editor.printer.clear_screen(line)?,
+        Action::AcceptLine => accept_line(editor)?,
+        Action::Interrupt => return Err(io::Error::from(io::ErrorKind::Interrupted)),
+        Action::ViCommandMode => {
+            line.move_left();
+            editor.mode = Mode::ViCommand;
+        }
+        Action::ViInsertMode => editor.mode = Mode::ViInsert,
+        Action::ViAppend => {
+            line.move_right();
+            editor.mode = Mode::ViInsert;
+        }
+        Action::ViAppendEol => {
+            line.set_position_end();
+            editor.mode = Mode::ViInsert;
+        }
+        Action::ViInsertBol => {
+            line.set_position_start_x();
+            editor.mode = Mode::ViInsert;
+        }
+        Action::StartMacro => {
+            if editor.macros.start() {
+                editor.status.flash("Defining keyboard macro...");
+            } else {
+                ding(editor, "Already defining a keyboard macro")?;
+            }
+        }
+        Action::EndMacro => match editor.macros.stop() {
+            Some(length) => {
+                editor
+                    .status
+                    .flash(&format!("Keyboard macro defined ({} actions)", length));
+            }
+            None => ding(editor, "Not defining a keyboard macro")?,
+        },
+        Action::CallLastMacro => {
+            if editor.macros.is_recording() {
+                ding(editor, "Cannot call the macro that is being defined")?;
+            } else {
+                match editor.macros.last() {
+                    Some(actions) => call_macro(editor, actions, 1)?,
+                    None => ding(editor, "No keyboard macro defined")?,
+                }
+            }
+        }
+        Action::CallMacro(name) => match editor.macros.get(name) {
+            Some(actions) => call_macro(editor, actions, 1)?,
+            None => ding(editor, &format!("No macro named {}", name))?,
+        },
+        Action::ToggleInspector => editor.inspector.visible = !editor.inspector.visible,
+        Action::ScrollUp | Action::ScrollDown => {
+            let page = editor.printer.page_height()? as isize;
+            let lines = if *action == Action::ScrollUp { page } else { -page };
+            if !editor.printer.scroll(line, lines)? {
+                ding(editor, "Cannot scroll the output further")?;
+            }
+        }
+        Action::DigitArgument(_) | Action::NegativeArgument | Action::UniversalArgument => (),
     }
     Ok(())
 }
 
-// MOD/ALT
-fn alt_b(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('b'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::ALT,
-        ..
-    }) = event
-    {
-        match line.left_word() {
-            None => debug_message("Could not move back word")?,
-            Some(x) => {
-                line.set_position_x(x);
-                io::stdout().execute(cursor::MoveTo(x + 2, line.position.y()))?;
-                debug_line(line)?;
-                debug_event(event)?;
-            }
+/// Pasted text as it can go into the line: newlines are kept, tabs become spaces and
+/// other control characters, which would reach the terminal as they are, are dropped.
+fn clean_paste(text: &str) -> String {
+    let text = text.replace("\r\n", "\n").replace('\r', "\n");
+    let mut result = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\n' => result.push(c),
+            '\t' => result.extend(std::iter::repeat_n(' ', TAB_WIDTH)),
+            c if c.is_control() => (),
+            c => result.push(c),
         }
     }
-    Ok(())
+    result
 }
-fn alt_f(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('f'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::ALT,
-        ..
-    }) = event
-    {
-        match line.right_word() {
-            None => debug_message("Could not move forward word")?,
-            Some(x) => {
-                line.set_position_x(x);
-                io::stdout().execute(cursor::MoveTo(x + 2, line.position.y()))?;
-                debug_line(line)?;
-                debug_event(event)?;
+
+/// Resolves one event through the keymap, `Interrupted` means the session is over.
+fn dispatch(event: &Event, editor: &mut Editor) -> io::Result<()> {
+    log::debug!("event {:?}", event);
+    let resolved = match event {
+        Event::Key(key_event) if key_event.kind != KeyEventKind::Release => {
+            let resolved = editor.keymap().resolve(Key::from(key_event));
+            log::debug!("resolved to {:?}", resolved);
+            Some(resolved)
+        }
+        _ => None,
+    };
+    // Events that do not go through the keymap are kept too, with nothing resolved.
+    let description = resolved
+        .as_ref()
+        .map_or(String::from("-"), |resolved| format!("{:?}", resolved));
+    editor.inspector.record(format!("{:?}", event), description);
+
+    match (event, resolved) {
+        (_, Some(Resolved::Action(action))) => return run_action(editor, &action),
+        // Shows the chord so far in the status bar.
+        (_, Some(Resolved::Pending)) => return editor.refresh(),
+        (_, Some(Resolved::Unbound(keys))) => {
+            let chord = keys.iter().map(Key::to_string).collect::<Vec<_>>();
+            ding(editor, &format!("{} is undefined", chord.join(" ")))?;
+            return editor.refresh();
+        }
+        (Event::Paste(text), None) => {
+            return run_action(editor, &Action::InsertText(clean_paste(text)));
+        }
+        (Event::Mouse(mouse), None) => {
+            let lines = match mouse.kind {
+                MouseEventKind::ScrollUp => 3,
+                MouseEventKind::ScrollDown => -3,
+                _ => 0,
+            };
+            if lines != 0 {
+                editor.printer.scroll(&mut editor.line, lines)?;
             }
         }
+        (Event::Resize(_, _), None) if editor.printer.is_fullscreen() => {
+            editor.printer.redraw(&mut editor.line)?;
+        }
+        _ => (),
+    }
+    // The open inspector shows the event even when nothing else changed.
+    if editor.inspector.visible {
+        editor.refresh()?;
     }
     Ok(())
 }
-fn alt_d(event: &Event, line: &mut CurrentLine) -> io::Result<()> {
-    if let Event::Key(KeyEvent {
-        code: KeyCode::Char('d'),
-        kind: KeyEventKind::Release,
-        modifiers: KeyModifiers::ALT,
-        ..
-    }) = event
-    {
-        line.push_right_word_to_history();
-        line.delete_word_right();
-        line.display()?;
-        debug_line(line)?;
-        debug_event(event)?;
+/// Loads the config and greets, problems with the config are printed but not fatal.
+fn start(line: CurrentLine, capabilities: Capabilities) -> io::Result<Editor> {
+    let (config, config_error) = match Config::load() {
+        Ok(config) => (config, None),
+        Err(e) => (
+            Config {
+                unreadable: true,
+                ..Config::default()
+            },
+            Some(e),
+        ),
+    };
+    let mut editor = Editor::new(line, config, capabilities);
+    editor.sync_aliases();
+    editor.print("Welcome to the crispy repl 😁!")?;
+    if let Some(e) = config_error {
+        editor.print_error(&format!("Could not load the config: {}", e))?;
     }
-    Ok(())
+    for chord in editor.load_bindings() {
+        editor.print_error(&format!("Cannot parse key {} in the config", chord))?;
+    }
+    for invalid in editor.load_options()? {
+        editor.print_error(&format!("Invalid option in the config, {}", invalid))?;
+    }
+    if !editor.load_theme() {
+        let message = format!(
+            "Unknown theme {}, the built-in themes are {}",
+            editor.config.theme,
+            Theme::BUILT_IN.join(", ")
+        );
+        editor.print_error(&message)?;
+    }
+    Ok(editor)
 }
-pub fn read_char() -> io::Result<()> {
-    io::stdout().execute(cursor::SetCursorStyle::BlinkingBlock)?;
-    println!("Welcome to the crispy repl {}!", "😁");
-    prompt()?;
 
+pub fn read_char(
+    source: EventSource,
+    recorder: Option<Recorder>,
+    capabilities: Capabilities,
+) -> io::Result<()> {
+    io::stdout().execute(cursor::SetCursorStyle::BlinkingBlock)?;
     let (x, y) = match cursor::position() {
         Ok((_, y)) => (0, y),
         _ => (0, 0),
     };
-    let mut line = CurrentLine::new(x, y);
+    let mut editor = start(CurrentLine::new(x, y), capabilities)?;
+    editor.input = Input::new(source, recorder);
+    editor.refresh()?;
 
     loop {
-        match event::read() {
-            Err(..) => (),
-            Ok(event) => {
-                regular_character(&event, &mut line)?;
-                backspace(&event, &mut line)?;
-                alt_b(&event, &mut line)?;
-                alt_f(&event, &mut line)?;
-                alt_d(&event, &mut line)?;
-                control_k(&event, &mut line)?;
-                control_l(&event, &mut line)?;
-                control_a(&event, &mut line)?;
-                control_e(&event, &mut line)?;
-                control_b(&event, &mut line)?;
-                control_f(&event, &mut line)?;
-                control_d(&event, &mut line)?;
-                match parse_line(&event, &mut line) {
-                    Err(..) => break,
-                    _ => (),
-                }
-                match control_c(&event) {
-                    Err(..) => break,
-                    _ => (),
-                }
+        editor.printer.print_external(&mut editor.line)?;
+        if editor.status.expire() {
+            editor.refresh()?;
+        }
+        let event = match editor.input.next(POLL_INTERVAL)? {
+            Some(event) => event,
+            None => continue,
+        };
+        if let Err(e) = dispatch(&event, &mut editor) {
+            if e.kind() == io::ErrorKind::Interrupted {
+                break;
             }
+            return Err(e);
         }
     }
 
     Ok(())
 }
+
+/// Reads whole lines in cooked mode for terminals that cannot do raw mode, the
+/// terminal does the editing and only commands are available.
+pub fn read_lines(capabilities: Capabilities) -> io::Result<()> {
+    let mut editor = start(CurrentLine::new(0, 0), capabilities)?;
+    let mut input = String::new();
+    loop {
+        editor.printer.print_external(&mut editor.line)?;
+        let (prompt, _) = editor.prompts();
+        print!("{}", prompt.text);
+        io::stdout().flush()?;
+
+        input.clear();
+        if io::stdin().read_line(&mut input)? == 0 {
+            println!();
+            return Ok(());
+        }
+        editor.line.clear();
+        editor.line.insert_str(input.trim_end_matches(['\r', '\n']));
+        if let Err(e) = accept_line(&mut editor) {
+            if e.kind() == io::ErrorKind::Interrupted {
+                return Ok(());
+            }
+            return Err(e);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use crossterm::terminal;
+use std::io;
+
+/// Rows of the full-screen layout, from the top: the output pane, the status bar, the
+/// input line and at the bottom the rows of the footer after the status bar.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layout {
+    pub columns: u16,
+    /// Height of the output pane, it starts at the first row.
+    pub output: u16,
+    pub status: u16,
+    pub input: u16,
+}
+impl Layout {
+    pub fn new(columns: u16, rows: u16, reserved: u16) -> Self {
+        let input = rows.saturating_sub(reserved + 1);
+        let status = input.saturating_sub(1);
+        Layout {
+            columns,
+            output: status,
+            status,
+            input,
+        }
+    }
+
+    /// The layout for the terminal size right now.
+    pub fn current(reserved: u16) -> io::Result<Self> {
+        let (columns, rows) = terminal::size()?;
+        Ok(Layout::new(columns, rows, reserved))
+    }
+}
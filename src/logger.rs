@@ -0,0 +1,54 @@
+use chrono::Local;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+/// Writes the log to a file, the terminal belongs to the editor.
+struct FileLogger {
+    file: Mutex<File>,
+    level: LevelFilter,
+}
+impl Log for FileLogger {
+    /// Libraries only get in with warnings and errors, their debug output drowns ours.
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.level
+            && (metadata.level() <= Level::Warn
+                || metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // A log that cannot be written must not take the editor down.
+        if let Ok(mut file) = self.file.lock() {
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+                record.level(),
+                record.target(),
+                record.args()
+            );
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Appends records up to `level` to the file at `path` for the rest of the session.
+pub fn init(path: &str, level: LevelFilter) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let logger = FileLogger {
+        file: Mutex::new(file),
+        level,
+    };
+    log::set_logger(Box::leak(Box::new(logger))).map_err(|e| io::Error::other(e.to_string()))?;
+    log::set_max_level(level);
+    Ok(())
+}
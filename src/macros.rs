@@ -0,0 +1,78 @@
+use super::keymap::Action;
+use std::collections::BTreeMap;
+
+/// How deep macros may call other macros before the call is refused.
+pub const MAX_DEPTH: usize = 8;
+
+/// Emacs style keyboard macros, recorded as editor actions.
+pub struct Macros {
+    recording: Option<Vec<Action>>,
+    last: Option<Vec<Action>>,
+    named: BTreeMap<String, Vec<Action>>,
+    pub depth: usize,
+}
+impl Macros {
+    pub fn new(named: BTreeMap<String, Vec<Action>>) -> Self {
+        Macros {
+            recording: None,
+            last: None,
+            named,
+            depth: 0,
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn start(&mut self) -> bool {
+        if self.is_recording() {
+            false
+        } else {
+            self.recording = Some(Vec::new());
+            true
+        }
+    }
+
+    /// Returns the number of recorded actions.
+    pub fn stop(&mut self) -> Option<usize> {
+        let actions = self.recording.take()?;
+        let length = actions.len();
+        self.last = Some(actions);
+        Some(length)
+    }
+
+    /// Actions run by a macro that is replayed are not recorded again.
+    pub fn record(&mut self, action: &Action) {
+        if self.depth > 0 {
+            return;
+        }
+        if let Some(actions) = self.recording.as_mut() {
+            actions.push(action.clone());
+        }
+    }
+
+    pub fn last(&self) -> Option<Vec<Action>> {
+        self.last.clone()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Vec<Action>> {
+        self.named.get(name).cloned()
+    }
+
+    pub fn name_last(&mut self, name: &str) -> bool {
+        match self.last.clone() {
+            Some(actions) => {
+                self.named.insert(name.to_string(), actions);
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = (&String, usize)> {
+        self.named
+            .iter()
+            .map(|(name, actions)| (name, actions.len()))
+    }
+}
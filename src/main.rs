@@ -1,19 +1,97 @@
+mod capabilities;
+mod config;
 mod currentline;
-mod debug;
+mod editor;
+mod highlight;
+mod history;
+mod inspector;
+mod keymap;
 mod keys;
+mod layout;
+mod logger;
+mod macros;
 mod my_parser;
+mod options;
+mod pager;
+mod printer;
+mod prompt;
+mod recording;
+mod status;
+mod suggest;
+mod theme;
+mod words;
 
+use capabilities::Capabilities;
 use crossterm::{
     cursor,
-    event::{DisableMouseCapture, EnableMouseCapture},
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
     execute,
     terminal::{self, disable_raw_mode, enable_raw_mode, ClearType},
     ExecutableCommand,
 };
-use keys::read_char;
-use std::io;
+use keys::{read_char, read_lines};
+use log::LevelFilter;
+use recording::{EventSource, Recorder, Replay};
+use std::{env, io, process};
+
+const USAGE: &str = "usage: crispy-fortnight [--record <file>] [--replay <file> [--realtime]] \
+[--log <file>] [--log-level off|error|warn|info|debug|trace]";
+
+fn parse_args() -> io::Result<(EventSource, Option<Recorder>)> {
+    let usage = || io::Error::new(io::ErrorKind::InvalidInput, USAGE);
+    let mut record = None;
+    let mut replay = None;
+    let mut realtime = false;
+    let mut log = env::var("CRISPY_LOG").ok();
+    let mut level = env::var("CRISPY_LOG_LEVEL").ok();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record = Some(args.next().ok_or_else(usage)?),
+            "--replay" => replay = Some(args.next().ok_or_else(usage)?),
+            "--realtime" => realtime = true,
+            "--log" => log = Some(args.next().ok_or_else(usage)?),
+            "--log-level" => level = Some(args.next().ok_or_else(usage)?),
+            _ => return Err(usage()),
+        }
+    }
+
+    let level = match level {
+        Some(level) => level.parse::<LevelFilter>().map_err(|_| usage())?,
+        None => LevelFilter::Info,
+    };
+    if let Some(path) = log.filter(|path| !path.is_empty()) {
+        logger::init(&path, level)?;
+    }
+
+    let source = match replay {
+        Some(path) => EventSource::Replay(Replay::open(&path, realtime)?),
+        None => EventSource::Terminal,
+    };
+    let recorder = match record {
+        Some(path) => Some(Recorder::create(&path)?),
+        None => None,
+    };
+    Ok((source, recorder))
+}
 
 fn main() -> io::Result<()> {
+    let (source, recorder) = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(2);
+        }
+    };
+    let capabilities = Capabilities::detect();
+    log::info!("starting with {:?}", capabilities);
+    if !capabilities.interactive {
+        if !matches!(source, EventSource::Terminal) || recorder.is_some() {
+            eprintln!("--record and --replay need a terminal that supports raw mode");
+            process::exit(2);
+        }
+        return read_lines(capabilities);
+    }
     let x = match cursor::position() {
         Ok((x, _)) => x,
         _ => 0,
@@ -21,9 +99,10 @@ fn main() -> io::Result<()> {
     enable_raw_mode()?;
 
     let mut stdout = io::stdout();
-    execute!(stdout, EnableMouseCapture)?;
+    execute!(stdout, EnableMouseCapture, EnableBracketedPaste)?;
 
-    if let Err(e) = read_char() {
+    if let Err(e) = read_char(source, recorder, capabilities) {
+        log::error!("the editor stopped: {:?}", e);
         println!("Error: {:?}\r", e);
     }
 
@@ -31,12 +110,11 @@ fn main() -> io::Result<()> {
         Ok((x, y)) => (x, y),
         _ => (0, 0),
     };
-    io::stdout().execute(terminal::Clear(ClearType::CurrentLine))?;
-    println!("\rBye {}!", "😁");
-    debug::debug_clear()?;
+    io::stdout().execute(terminal::Clear(ClearType::FromCursorDown))?;
+    println!("\rBye 😁!");
 
     io::stdout().execute(cursor::SetCursorStyle::DefaultUserShape)?;
-    execute!(stdout, DisableMouseCapture)?;
+    execute!(stdout, DisableMouseCapture, DisableBracketedPaste)?;
 
     if x == 0 {
         io::stdout().execute(cursor::MoveTo(xp, yp))?;
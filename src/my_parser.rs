@@ -1,20 +1,97 @@
-use super::debug::debug_message;
-use std::io::{self, Write};
+use std::collections::BTreeMap;
+use std::fmt;
 
-pub fn print_help() -> io::Result<()> {
-    print!("\nHelp!");
-    debug_message("Print Help!")?;
-    io::stdout().flush()?;
+/// Shown by `:h`.
+pub const HELP: &str = "\
+Commands:
+  :h                          show this help
+  :q, :quit                   leave the editor
+  :load <file>                run every line of a file as a command
+  :timer <seconds>            count down above the prompt
+  :macro                      list the named macros
+  :macro name <name>          name the last recorded macro
+  :macro call <name> [count]  run a named macro
+  :macro bind <name> <keys>   bind a named macro to a key chord
+  :macro save <name>          save a named macro to the config
+  :set <name> <value>         set a variable, commands use it as $name
+  :unset <name>               remove a variable
+  :vars                       list the variables
+  :alias                      list the aliases
+  :alias <name>=<body>        define an alias, $1-$9 and $@ are its arguments
+  :unalias <name>             remove an alias
+  :option                     list the options
+  :option <name> [value]      show or change an option
+  :option reset <name>        go back to the configured value
+  :!<command>, :sh <command>  run a command with $SHELL
+  :capture <command>          insert the output of a command at the cursor
 
-    Ok(())
-}
+Commands are chained with ;, && and ||. Alias bodies are chained the same way,
+\\; keeps a ; in the body when the alias is defined. A shell command takes the
+rest of the line, :!make && ./run runs both in the shell.";
+
+/// Names of the commands `parse` knows, without the leading `:`.
+pub const COMMANDS: &[&str] = &[
+    "h", "q", "quit", "load", "timer", "macro", "set", "unset", "vars", "alias", "unalias",
+    "option", "sh", "capture",
+];
 
 pub enum Command {
     Help,
     Quit,
-    Load(String),
+    /// A command `parse` does not know, with the words it was split into.
+    Unknown(String),
+    Timer(u64),
+    Macro(MacroCommand),
+    /// Runs every line of a file as a command.
+    Script(String),
+    Set(String, String),
+    Unset(String),
+    Vars,
+    Alias(AliasCommand),
+    Option(OptionCommand),
+    /// Runs a command line with `$SHELL -c`, captured output goes into the input line.
+    Shell { command: String, capture: bool },
     Invalid,
-    Command(Box<Command>),
+}
+
+pub enum OptionCommand {
+    List,
+    Show(String),
+    Set(String, String),
+    Reset(String),
+}
+
+pub enum AliasCommand {
+    List,
+    Define(String, String),
+    Remove(String),
+}
+
+pub enum MacroCommand {
+    List,
+    Name(String),
+    Call(String, usize),
+    Bind(String, String),
+    Save(String),
+}
+
+fn parse_macro(arguments: &str) -> Command {
+    let words = arguments.split_whitespace().collect::<Vec<_>>();
+    let command = match words.as_slice() {
+        [] => MacroCommand::List,
+        ["name", name] => MacroCommand::Name(name.to_string()),
+        ["call", name] => MacroCommand::Call(name.to_string(), 1),
+        ["call", name, count] => match count.parse() {
+            Ok(count) => MacroCommand::Call(name.to_string(), count),
+            Err(_) => return Command::Invalid,
+        },
+        ["bind", name, keys @ ..] if !keys.is_empty() => {
+            MacroCommand::Bind(name.to_string(), keys.join(" "))
+        }
+        ["save", name] => MacroCommand::Save(name.to_string()),
+        _ => return Command::Invalid,
+    };
+    Command::Macro(command)
 }
 
 #[derive(Debug)]
@@ -32,21 +109,23 @@ impl CommandMap {
 
     fn add(&mut self, value: &str) {
         let tmp = String::from(value.trim());
-        if tmp != "" {
+        if !tmp.is_empty() {
             self.arg.push(tmp);
         }
     }
-
-    fn to_string(&self) -> String {
-        if self.arg.len() == 0 {
-            format!("{{key: {}}}", self.key)
+}
+impl fmt::Display for CommandMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.arg.is_empty() {
+            write!(f, "{{key: {}}}", self.key)
         } else {
             let args: String = self.arg.iter().fold(String::from(""), |mut acc, value| {
-                acc += &value;
+                acc += value;
                 acc += " ";
                 acc
             });
-            format!(
+            write!(
+                f,
                 "{{key: {}, {}: {}}}",
                 self.key,
                 if self.arg.len() == 1 { "arg" } else { "args" },
@@ -55,11 +134,340 @@ impl CommandMap {
         }
     }
 }
+/// When a command in a chain runs, depending on the status of the command before.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Chain {
+    /// First command or after `;`.
+    Always,
+    /// After `&&`.
+    OnSuccess,
+    /// After `||`.
+    OnFailure,
+}
+
+/// Lines for the shell, which expands its own `$`.
+pub fn is_shell_command(line: &str) -> bool {
+    line.starts_with(":!") || line.starts_with(":sh ") || line.starts_with(":capture ")
+}
+
+/// Whether `$name` is filled in before the line is parsed.
+///
+/// The shell expands its own `$` and an alias body keeps its `$` until the alias is used.
+pub fn interpolates(line: &str) -> bool {
+    !is_shell_command(line) && line != ":alias" && !line.starts_with(":alias ")
+}
+
+/// Splits a line at `;`, `&&` and `||` outside of quotes.
+///
+/// `\;`, `\&` and `\|` outside of quotes are plain characters, quoted text is
+/// kept as typed. Blank commands between the separators are left out, a blank
+/// line is a single blank command.
+/// A shell command takes the rest of the line, the shell chains it itself.
+pub fn split_chain(line: &str) -> Vec<(Chain, String)> {
+    let mut commands = Vec::new();
+    let mut chain = Chain::Always;
+    let mut command = String::new();
+    let mut quote = None;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if command.trim().is_empty() && is_shell_command(&line[i..]) {
+            command = line[i..].to_string();
+            break;
+        }
+        let next = match (c, quote) {
+            ('\\', None) if matches!(chars.peek(), Some((_, ';' | '&' | '|'))) => {
+                command.extend(chars.next().map(|(_, c)| c));
+                continue;
+            }
+            ('"' | '\'', None) => {
+                quote = Some(c);
+                None
+            }
+            (c, Some(open)) if c == open => {
+                quote = None;
+                None
+            }
+            (';', None) => Some(Chain::Always),
+            ('&', None) if matches!(chars.peek(), Some((_, '&'))) => Some(Chain::OnSuccess),
+            ('|', None) if matches!(chars.peek(), Some((_, '|'))) => Some(Chain::OnFailure),
+            _ => None,
+        };
+        match next {
+            Some(next) => {
+                if next != Chain::Always {
+                    chars.next();
+                }
+                if !command.trim().is_empty() {
+                    commands.push((chain, command.trim().to_string()));
+                }
+                command.clear();
+                chain = next;
+            }
+            None => command.push(c),
+        }
+    }
+    if !command.trim().is_empty() || commands.is_empty() {
+        commands.push((chain, command.trim().to_string()));
+    }
+    commands
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Names start with a letter or `_`, so `$1` and `$@` are left for aliases.
+fn is_variable_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_') && name.chars().all(is_name_char)
+}
+
+/// Alias names are used like commands, `:alias` and `:unalias` cannot be replaced.
+fn is_alias_name(name: &str) -> bool {
+    !name.is_empty() && !name.contains(char::is_whitespace) && name != "alias" && name != "unalias"
+}
+
+/// `name = body` defines an alias, nothing lists them.
+fn parse_alias(arguments: &str) -> Command {
+    if arguments.is_empty() {
+        return Command::Alias(AliasCommand::List);
+    }
+    match arguments.split_once('=') {
+        Some((name, body)) => {
+            let name = name.trim();
+            let name = name.strip_prefix(':').unwrap_or(name);
+            if is_alias_name(name) && !body.trim().is_empty() {
+                Command::Alias(AliasCommand::Define(
+                    name.to_string(),
+                    body.trim().to_string(),
+                ))
+            } else {
+                Command::Invalid
+            }
+        }
+        None => Command::Invalid,
+    }
+}
+
+/// The commands of an alias body with `$1` to `$9` replaced by the arguments and `$@` by all of them.
+///
+/// The body is split before the arguments go in, so a `;` in them never starts a command.
+/// Without any of these in the body the arguments are appended to its last command.
+fn substitute(body: &str, arguments: &str) -> Vec<(Chain, String)> {
+    let words = arguments.split_whitespace().collect::<Vec<_>>();
+    let mut commands = Vec::new();
+    let mut positional = false;
+    for (chain, command) in split_chain(body) {
+        let mut result = String::new();
+        let mut chars = command.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('$', Some('@')) => result += arguments,
+                ('$', Some(&digit)) if ('1'..='9').contains(&digit) => {
+                    let index = digit as usize - '1' as usize;
+                    result += words.get(index).unwrap_or(&"");
+                }
+                _ => {
+                    result.push(c);
+                    continue;
+                }
+            }
+            positional = true;
+            chars.next();
+        }
+        commands.push((chain, result));
+    }
+    if !positional && !arguments.is_empty() {
+        if let Some((_, last)) = commands.last_mut() {
+            last.push(' ');
+            *last += arguments;
+        }
+    }
+    commands
+}
+
+/// `name value` changes an option, `reset name` goes back to the configured value.
+fn parse_option(arguments: &str) -> Command {
+    let command = match arguments.split_once(' ') {
+        None if arguments.is_empty() => OptionCommand::List,
+        None => OptionCommand::Show(arguments.to_string()),
+        Some(("reset", name)) => OptionCommand::Reset(name.trim().to_string()),
+        Some((name, value)) => OptionCommand::Set(name.to_string(), value.trim().to_string()),
+    };
+    Command::Option(command)
+}
+
+/// `name value`, the value is the rest of the line and may be empty.
+fn parse_set(arguments: &str) -> Command {
+    let (name, value) = arguments.split_once(' ').unwrap_or((arguments, ""));
+    if is_variable_name(name) {
+        Command::Set(name.to_string(), value.trim_start().to_string())
+    } else {
+        Command::Invalid
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InterpolateError {
+    Undefined(String),
+    /// A `${` without the closing `}`.
+    Unterminated,
+}
+impl fmt::Display for InterpolateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterpolateError::Undefined(name) => write!(f, "Undefined variable {}", name),
+            InterpolateError::Unterminated => write!(f, "Missing }} after ${{"),
+        }
+    }
+}
+
+/// Replaces `$name` and `${name}` with the values from `variables`, `\$` is a literal `$`.
+///
+/// A `$` that is not followed by a name, like in `$1`, stays as it is.
+pub fn interpolate(
+    line: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<String, InterpolateError> {
+    let mut result = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'$') => {
+                result.push('$');
+                chars.next();
+            }
+            '$' => {
+                let name = if chars.peek() == Some(&'{') {
+                    chars.next();
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => return Err(InterpolateError::Unterminated),
+                        }
+                    }
+                    name
+                } else {
+                    let mut name = String::new();
+                    while let Some(&c) = chars.peek() {
+                        if !is_name_char(c) || (name.is_empty() && c.is_numeric()) {
+                            break;
+                        }
+                        name.push(c);
+                        chars.next();
+                    }
+                    if name.is_empty() {
+                        result.push('$');
+                        continue;
+                    }
+                    name
+                };
+                match variables.get(&name) {
+                    Some(value) => result += value,
+                    None => return Err(InterpolateError::Undefined(name)),
+                }
+            }
+            c => result.push(c),
+        }
+    }
+    Ok(result)
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Expansion {
+    /// The command is not an alias.
+    None,
+    /// The name of the alias and the commands of its body with the arguments filled in.
+    Alias(String, Vec<(Chain, String)>),
+    /// Aliases that expand into each other, in the order they were expanded.
+    Loop(Vec<String>),
+}
+
+/// Expands a command that is an alias, `expanding` are the aliases whose bodies are running.
+///
+/// An alias whose body uses its own name, directly or through other aliases,
+/// runs the built-in command of that name, or is a loop if there is none.
+pub fn expand_alias(
+    line: &str,
+    aliases: &BTreeMap<String, String>,
+    expanding: &[String],
+) -> Expansion {
+    let (word, arguments) = line.split_once(' ').unwrap_or((line, ""));
+    let name = match word.strip_prefix(':') {
+        Some(name) if is_alias_name(name) => name,
+        _ => return Expansion::None,
+    };
+    let body = match aliases.get(name) {
+        Some(body) => body,
+        None => return Expansion::None,
+    };
+    if expanding.iter().any(|seen| seen == name) {
+        if COMMANDS.contains(&name) {
+            return Expansion::None;
+        }
+        let mut names = expanding.to_vec();
+        names.push(name.to_string());
+        return Expansion::Loop(names);
+    }
+    Expansion::Alias(name.to_string(), substitute(body, arguments.trim()))
+}
+
+fn parse_shell(command: &str, capture: bool) -> Command {
+    match command.trim() {
+        "" => Command::Invalid,
+        command => Command::Shell {
+            command: command.to_string(),
+            capture,
+        },
+    }
+}
+
+/// Parses a single command, aliases are expanded before.
 pub fn parse(string: String) -> Command {
     if string == ":h" {
         Command::Help
-    } else if string.contains(":quit") || string.contains(":q") {
+    } else if let Some(command) = string.strip_prefix(":!") {
+        parse_shell(command, false)
+    } else if string == ":sh" || string.starts_with(":sh ") {
+        parse_shell(&string[":sh".len()..], false)
+    } else if string == ":capture" || string.starts_with(":capture ") {
+        parse_shell(&string[":capture".len()..], true)
+    } else if let Some(arguments) = string.strip_prefix(":set ") {
+        parse_set(arguments.trim())
+    } else if let Some(name) = string.strip_prefix(":unset ") {
+        match name.trim() {
+            name if is_variable_name(name) => Command::Unset(name.to_string()),
+            _ => Command::Invalid,
+        }
+    } else if string == ":option" || string.starts_with(":option ") {
+        parse_option(string[":option".len()..].trim())
+    } else if string == ":vars" {
+        Command::Vars
+    } else if string == ":alias" || string.starts_with(":alias ") {
+        parse_alias(string[":alias".len()..].trim())
+    } else if let Some(name) = string.strip_prefix(":unalias ") {
+        match name.trim() {
+            name if is_alias_name(name) => Command::Alias(AliasCommand::Remove(name.to_string())),
+            _ => Command::Invalid,
+        }
+    } else if let Some(path) = string.strip_prefix(":load ") {
+        match path.trim() {
+            "" => Command::Invalid,
+            path => Command::Script(path.to_string()),
+        }
+    } else if string == ":macro" || string.starts_with(":macro ") {
+        parse_macro(&string[":macro".len()..])
+    } else if string == ":q" || string == ":quit" {
         Command::Quit
+    } else if let Some(seconds) = string.strip_prefix(":timer ") {
+        match seconds.trim().parse() {
+            Ok(seconds) => Command::Timer(seconds),
+            Err(_) => Command::Invalid,
+        }
+    } else if COMMANDS.contains(&string.trim_start_matches(':')) {
+        // A known command without the arguments it needs.
+        Command::Invalid
     } else {
         let mut keys: Vec<CommandMap> = Vec::new();
         string
@@ -72,24 +480,20 @@ pub fn parse(string: String) -> Command {
                     vec![x]
                 }
             })
-            .map(|vec| {
+            .flat_map(|vec| {
                 if vec.len() == 1 {
                     vec![("arg", vec[0].to_string())]
-                } else if vec[0] == "" {
+                } else if vec[0].is_empty() {
                     vec![("key", vec[1].to_string())]
                 } else {
                     vec![("arg", vec[0].to_string()), ("key", vec[1].to_string())]
                 }
             })
-            .flatten()
             .for_each(|(s, v)| {
                 if s == "key" {
                     keys.push(CommandMap::new(&v));
-                } else {
-                    if keys.len() > 0 {
-                        let last_index = keys.len() - 1;
-                        keys[last_index].add(&v);
-                    }
+                } else if let Some(last) = keys.last_mut() {
+                    last.add(&v);
                 }
             });
 
@@ -101,10 +505,281 @@ pub fn parse(string: String) -> Command {
                 acc
             });
 
-        if result == "" {
+        if result.is_empty() {
             Command::Invalid
         } else {
-            Command::Load(result)
+            Command::Unknown(result)
         }
     }
 }
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum TokenKind {
+    Space,
+    /// A word starting with `:`.
+    Command,
+    Flag,
+    Str {
+        terminated: bool,
+    },
+    Path,
+    Word,
+}
+
+/// A part of the line, `start` and `end` are byte offsets.
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+fn word_kind(word: &str) -> TokenKind {
+    if word.len() > 1 && word.starts_with(':') {
+        TokenKind::Command
+    } else if word.len() > 1 && word.starts_with('-') && word.parse::<f64>().is_err() {
+        TokenKind::Flag
+    } else if word.contains('/') || word.starts_with('~') {
+        TokenKind::Path
+    } else {
+        TokenKind::Word
+    }
+}
+
+/// Splits `line` into tokens starting at byte offset `from`, which has to be a token boundary.
+pub fn tokenize(line: &str, from: usize) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut chars = line[from..]
+        .char_indices()
+        .map(|(i, c)| (i + from, c))
+        .peekable();
+
+    while let Some((start, c)) = chars.next() {
+        let mut end = start + c.len_utf8();
+        let kind = if c.is_whitespace() {
+            while let Some(&(i, c)) = chars.peek() {
+                if !c.is_whitespace() {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            TokenKind::Space
+        } else if c == '"' || c == '\'' {
+            let mut terminated = false;
+            let mut escaped = false;
+            for (i, next) in chars.by_ref() {
+                end = i + next.len_utf8();
+                if escaped {
+                    escaped = false;
+                } else if next == '\\' {
+                    escaped = true;
+                } else if next == c {
+                    terminated = true;
+                    break;
+                }
+            }
+            TokenKind::Str { terminated }
+        } else {
+            while let Some(&(i, c)) = chars.peek() {
+                if c.is_whitespace() || c == '"' || c == '\'' {
+                    break;
+                }
+                end = i + c.len_utf8();
+                chars.next();
+            }
+            word_kind(&line[start..end])
+        };
+        tokens.push(Token { kind, start, end });
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            (String::from("name"), String::from("world")),
+            (String::from("dir"), String::from("/tmp")),
+        ])
+    }
+
+    #[test]
+    fn interpolate_fills_in_names() {
+        let variables = variables();
+        let filled = |line| interpolate(line, &variables);
+        assert_eq!(filled("hello $name!"), Ok(String::from("hello world!")));
+        assert_eq!(filled("${dir}/x"), Ok(String::from("/tmp/x")));
+        assert_eq!(filled("$name$dir"), Ok(String::from("world/tmp")));
+    }
+
+    #[test]
+    fn interpolate_keeps_escaped_and_positional_dollars() {
+        let variables = variables();
+        let filled = |line| interpolate(line, &variables);
+        assert_eq!(filled("\\$name"), Ok(String::from("$name")));
+        assert_eq!(filled("$1 $@ $"), Ok(String::from("$1 $@ $")));
+        assert_eq!(filled("costs 5$"), Ok(String::from("costs 5$")));
+    }
+
+    #[test]
+    fn interpolate_reports_errors() {
+        let variables = variables();
+        let filled = |line| interpolate(line, &variables);
+        assert_eq!(
+            filled("$missing"),
+            Err(InterpolateError::Undefined(String::from("missing")))
+        );
+        assert_eq!(filled("${name"), Err(InterpolateError::Unterminated));
+    }
+
+    fn aliases(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(name, body)| (name.to_string(), body.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn substitute_fills_in_arguments() {
+        let filled = |body, arguments| substitute(body, arguments).remove(0).1;
+        assert_eq!(filled(":!cp $1 $2", "a b"), ":!cp a b");
+        assert_eq!(filled(":!echo $@", "a  b"), ":!echo a  b");
+        assert_eq!(filled(":!echo $2$1", "a"), ":!echo a");
+        assert_eq!(filled(":!ls", "-l /tmp"), ":!ls -l /tmp");
+        assert_eq!(filled(":!ls", ""), ":!ls");
+        // $0 and $name are not arguments.
+        assert_eq!(filled(":set x $0 $name", "a"), ":set x $0 $name a");
+    }
+
+    #[test]
+    fn substitute_splits_the_body_first() {
+        assert_eq!(
+            substitute(":set x $1; :vars", "a;b"),
+            commands(&[(Chain::Always, ":set x a;b"), (Chain::Always, ":vars")])
+        );
+        assert_eq!(
+            substitute(":h && :set x", "a || b"),
+            commands(&[(Chain::Always, ":h"), (Chain::OnSuccess, ":set x a || b")])
+        );
+    }
+
+    #[test]
+    fn expand_alias_substitutes_the_body() {
+        let aliases = aliases(&[("ll", ":!ls -l $1"), ("say", ":set said $1")]);
+        assert_eq!(
+            expand_alias(":ll /tmp", &aliases, &[]),
+            Expansion::Alias(
+                String::from("ll"),
+                commands(&[(Chain::Always, ":!ls -l /tmp")])
+            )
+        );
+        // `\;` typed at the prompt arrives as a plain `;` and stays one.
+        assert_eq!(
+            expand_alias(&split_chain(":say a\\;b").remove(0).1, &aliases, &[]),
+            Expansion::Alias(
+                String::from("say"),
+                commands(&[(Chain::Always, ":set said a;b")])
+            )
+        );
+        assert_eq!(expand_alias(":l /tmp", &aliases, &[]), Expansion::None);
+        assert_eq!(expand_alias("ll /tmp", &aliases, &[]), Expansion::None);
+    }
+
+    #[test]
+    fn expand_alias_finds_loops() {
+        let aliases = aliases(&[("a", ":b"), ("b", ":a"), ("h", ":h; :vars")]);
+        let expanding = [String::from("a"), String::from("b")];
+        assert_eq!(
+            expand_alias(":a", &aliases, &expanding),
+            Expansion::Loop(vec![
+                String::from("a"),
+                String::from("b"),
+                String::from("a")
+            ])
+        );
+        // An alias named like a command runs the command inside its own body.
+        assert_eq!(
+            expand_alias(":h", &aliases, &[String::from("h")]),
+            Expansion::None
+        );
+    }
+
+    #[test]
+    fn parse_quits_only_on_the_quit_commands() {
+        assert!(matches!(parse(String::from(":q")), Command::Quit));
+        assert!(matches!(parse(String::from(":quit")), Command::Quit));
+        assert!(matches!(parse(String::from(":qux")), Command::Unknown(_)));
+        assert!(matches!(
+            parse(String::from(":set x a:q")),
+            Command::Set(name, value) if name == "x" && value == "a:q"
+        ));
+    }
+
+    fn commands(commands: &[(Chain, &str)]) -> Vec<(Chain, String)> {
+        commands
+            .iter()
+            .map(|(chain, command)| (*chain, command.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn split_chain_splits_at_operators() {
+        assert_eq!(
+            split_chain(":h; :vars && :q || :h"),
+            commands(&[
+                (Chain::Always, ":h"),
+                (Chain::Always, ":vars"),
+                (Chain::OnSuccess, ":q"),
+                (Chain::OnFailure, ":h")
+            ])
+        );
+        // A single & or | is not an operator.
+        assert_eq!(
+            split_chain(":set x a&b|c"),
+            commands(&[(Chain::Always, ":set x a&b|c")])
+        );
+    }
+
+    #[test]
+    fn split_chain_keeps_quoted_and_escaped_operators() {
+        assert_eq!(
+            split_chain(":set x \"a; b\" 'c && d'"),
+            commands(&[(Chain::Always, ":set x \"a; b\" 'c && d'")])
+        );
+        assert_eq!(
+            split_chain(":set x a\\; b\\&\\& c"),
+            commands(&[(Chain::Always, ":set x a; b&& c")])
+        );
+        assert_eq!(
+            split_chain(":set x \"a\\;b\"; :vars"),
+            commands(&[(Chain::Always, ":set x \"a\\;b\""), (Chain::Always, ":vars")])
+        );
+    }
+
+    #[test]
+    fn split_chain_drops_blank_commands() {
+        assert_eq!(
+            split_chain(" ;; :h ;  && :vars"),
+            commands(&[(Chain::Always, ":h"), (Chain::OnSuccess, ":vars")])
+        );
+        assert_eq!(split_chain("   "), commands(&[(Chain::Always, "")]));
+    }
+
+    #[test]
+    fn split_chain_leaves_shell_commands_whole() {
+        assert_eq!(
+            split_chain(":h && :!make && ./run; echo done"),
+            commands(&[
+                (Chain::Always, ":h"),
+                (Chain::OnSuccess, ":!make && ./run; echo done")
+            ])
+        );
+        assert_eq!(
+            split_chain(":capture date || true"),
+            commands(&[(Chain::Always, ":capture date || true")])
+        );
+    }
+}
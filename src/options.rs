@@ -0,0 +1,284 @@
+use super::config::Config;
+use super::words::WordStyle;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Where the current value of an option comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    Config,
+    /// Changed with `:option` in this session.
+    Runtime,
+}
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::Config => write!(f, "config"),
+            Source::Runtime => write!(f, "runtime"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditingMode {
+    Emacs,
+    Vi,
+}
+
+/// What happens when a key cannot do anything.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BellStyle {
+    None,
+    Audible,
+    /// Flashes the screen.
+    Visible,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CompletionStyle {
+    /// Suggestions are shown as ghost text after the cursor.
+    Inline,
+    None,
+}
+
+/// Where output that does not fit on the screen goes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PagerStyle {
+    Builtin,
+    /// Piped into `$PAGER`, the built-in pager is used when it is not set.
+    External,
+    /// Printed into the scrollback like short output.
+    Off,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutStyle {
+    /// The prompt is a line in the terminal, output scrolls by above it.
+    Inline,
+    /// Output pane, status bar and input line on the alternate screen.
+    Fullscreen,
+}
+
+/// Settings that can change while the editor runs, by name with `:option`.
+#[derive(Clone, Debug)]
+pub struct Options {
+    pub editing_mode: EditingMode,
+    pub history_size: usize,
+    pub bell_style: BellStyle,
+    pub completion_style: CompletionStyle,
+    pub word_separators: WordStyle,
+    pub pager: PagerStyle,
+    /// Longer output is paged, `None` pages output that is higher than the terminal.
+    pub pager_threshold: Option<usize>,
+    pub layout: LayoutStyle,
+    /// Options missing here have their default value.
+    sources: BTreeMap<&'static str, Source>,
+}
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            editing_mode: EditingMode::Emacs,
+            history_size: 1000,
+            bell_style: BellStyle::None,
+            completion_style: CompletionStyle::Inline,
+            word_separators: WordStyle::Readline,
+            pager: PagerStyle::Builtin,
+            pager_threshold: None,
+            layout: LayoutStyle::Inline,
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+fn choice<T: Copy>(value: &str, choices: &[(&str, T)]) -> Result<T, String> {
+    match choices.iter().find(|(name, _)| *name == value) {
+        Some(&(_, choice)) => Ok(choice),
+        None => {
+            let names = choices.iter().map(|(name, _)| *name).collect::<Vec<_>>();
+            let (last, rest) = names.split_last().unwrap_or((&"", &[]));
+            Err(format!("expected {} or {}", rest.join(", "), last))
+        }
+    }
+}
+
+impl Options {
+    pub const NAMES: [&'static str; 8] = [
+        "editing-mode",
+        "history-size",
+        "bell-style",
+        "completion-style",
+        "word-separators",
+        "pager",
+        "pager-threshold",
+        "layout",
+    ];
+
+    /// Options that are gone, configs that still set them keep working.
+    const REMOVED: [&'static str; 1] = ["debug-overlay"];
+
+    /// The options from the config, with the invalid ones and why they are invalid.
+    pub fn from_config(config: &Config) -> (Options, Vec<String>) {
+        let mut options = Options::default();
+        if config.words != WordStyle::default() {
+            options.word_separators = config.words.clone();
+            options.sources.insert("word-separators", Source::Config);
+        }
+        let mut invalid = Vec::new();
+        for (name, value) in &config.options {
+            if Options::REMOVED.contains(&name.as_str()) {
+                log::warn!("ignoring the removed option {} in the config", name);
+                continue;
+            }
+            if let Err(e) = options.set(name, value, Source::Config) {
+                invalid.push(format!("{}: {}", name, e));
+            }
+        }
+        (options, invalid)
+    }
+
+    /// The value of an option as it is written in `:option` and the config.
+    pub fn get(&self, name: &str) -> Option<String> {
+        let value = match name {
+            "editing-mode" => match self.editing_mode {
+                EditingMode::Emacs => "emacs",
+                EditingMode::Vi => "vi",
+            }
+            .to_string(),
+            "history-size" => self.history_size.to_string(),
+            "bell-style" => match self.bell_style {
+                BellStyle::None => "none",
+                BellStyle::Audible => "audible",
+                BellStyle::Visible => "visible",
+            }
+            .to_string(),
+            "completion-style" => match self.completion_style {
+                CompletionStyle::Inline => "inline",
+                CompletionStyle::None => "none",
+            }
+            .to_string(),
+            "word-separators" => match &self.word_separators {
+                WordStyle::Readline => String::from("readline"),
+                WordStyle::ViWord => String::from("vi-word"),
+                WordStyle::ViBigWord => String::from("vi-big-word"),
+                WordStyle::Separators(separators) => separators.clone(),
+            },
+            "pager" => match self.pager {
+                PagerStyle::Builtin => "builtin",
+                PagerStyle::External => "external",
+                PagerStyle::Off => "off",
+            }
+            .to_string(),
+            "pager-threshold" => match self.pager_threshold {
+                Some(lines) => lines.to_string(),
+                None => String::from("auto"),
+            },
+            "layout" => match self.layout {
+                LayoutStyle::Inline => "inline",
+                LayoutStyle::Fullscreen => "fullscreen",
+            }
+            .to_string(),
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    pub fn source(&self, name: &str) -> Source {
+        self.sources.get(name).copied().unwrap_or(Source::Default)
+    }
+
+    /// Parses and stores a value, the error tells which values are allowed.
+    pub fn set(&mut self, name: &str, value: &str, source: Source) -> Result<(), String> {
+        match name {
+            "editing-mode" => {
+                self.editing_mode = choice(
+                    value,
+                    &[("emacs", EditingMode::Emacs), ("vi", EditingMode::Vi)],
+                )?
+            }
+            "history-size" => match value.parse() {
+                Ok(size) if size > 0 => self.history_size = size,
+                _ => return Err(String::from("expected a positive number")),
+            },
+            "bell-style" => {
+                self.bell_style = choice(
+                    value,
+                    &[
+                        ("none", BellStyle::None),
+                        ("audible", BellStyle::Audible),
+                        ("visible", BellStyle::Visible),
+                    ],
+                )?
+            }
+            "completion-style" => {
+                self.completion_style = choice(
+                    value,
+                    &[
+                        ("inline", CompletionStyle::Inline),
+                        ("none", CompletionStyle::None),
+                    ],
+                )?
+            }
+            "word-separators" => {
+                self.word_separators = match value {
+                    "" => {
+                        return Err(String::from(
+                            "expected readline, vi-word, vi-big-word or separator characters",
+                        ))
+                    }
+                    "readline" => WordStyle::Readline,
+                    "vi-word" => WordStyle::ViWord,
+                    "vi-big-word" => WordStyle::ViBigWord,
+                    separators => WordStyle::Separators(separators.to_string()),
+                }
+            }
+            "pager" => {
+                self.pager = choice(
+                    value,
+                    &[
+                        ("builtin", PagerStyle::Builtin),
+                        ("external", PagerStyle::External),
+                        ("off", PagerStyle::Off),
+                    ],
+                )?
+            }
+            "pager-threshold" => match value {
+                "auto" => self.pager_threshold = None,
+                value => match value.parse() {
+                    Ok(lines) => self.pager_threshold = Some(lines),
+                    _ => return Err(String::from("expected auto or a number of lines")),
+                },
+            },
+            "layout" => {
+                self.layout = choice(
+                    value,
+                    &[
+                        ("inline", LayoutStyle::Inline),
+                        ("fullscreen", LayoutStyle::Fullscreen),
+                    ],
+                )?
+            }
+            _ => return Err(String::from("no such option")),
+        }
+        match Options::NAMES.iter().find(|&&known| known == name) {
+            Some(&name) if source != Source::Default => {
+                self.sources.insert(name, source);
+            }
+            Some(&name) => {
+                self.sources.remove(name);
+            }
+            None => (),
+        }
+        Ok(())
+    }
+
+    /// Goes back to the value from the config, or to the default.
+    pub fn reset(&mut self, name: &str, config: &Config) -> Result<(), String> {
+        let (configured, _) = Options::from_config(config);
+        match configured.get(name) {
+            Some(value) => self.set(name, &value, configured.source(name)),
+            None => Err(String::from("no such option")),
+        }
+    }
+}
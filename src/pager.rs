@@ -0,0 +1,250 @@
+use super::printer::Printer;
+use super::recording::{Input, POLL_INTERVAL};
+use super::theme::Theme;
+use crossterm::{
+    cursor,
+    event::{Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseEventKind},
+    queue,
+    style::Print,
+    terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use std::io::{self, Write};
+use unicode_width::UnicodeWidthChar;
+
+const HINT: &str = "q quit, / search, n/N next/previous match";
+
+/// The part of `line` that fits into `columns`, with tabs expanded.
+fn fit(line: &str, columns: usize) -> String {
+    let mut result = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        let (c, repeat) = match c {
+            '\t' => (' ', 4 - width % 4),
+            c => (c, 1),
+        };
+        let w = c.width().unwrap_or(0) * repeat;
+        if width + w > columns {
+            break;
+        }
+        result.extend(std::iter::repeat_n(c, repeat));
+        width += w;
+    }
+    result
+}
+
+/// Scrollable view of long output on the alternate screen, the scrollback is left alone.
+pub struct Pager<'a> {
+    lines: Vec<&'a str>,
+    theme: &'a Theme,
+    /// Index of the first line on the screen.
+    top: usize,
+    pattern: Option<String>,
+    /// Shown in the status line instead of the hint until the next key.
+    message: Option<String>,
+}
+impl<'a> Pager<'a> {
+    pub fn new(text: &'a str, theme: &'a Theme) -> Self {
+        Pager {
+            lines: text.lines().collect(),
+            theme,
+            top: 0,
+            pattern: None,
+            message: None,
+        }
+    }
+
+    /// Shows the text until `q` is pressed, then switches back to the editor's screen.
+    ///
+    /// Messages from other threads show in the status line meanwhile and are
+    /// printed once the pager is closed.
+    pub fn run(&mut self, input: &mut Input, printer: &mut Printer) -> io::Result<()> {
+        let mut stdout = io::stdout();
+        queue!(stdout, EnterAlternateScreen, cursor::Hide)?;
+        let result = self.event_loop(&mut stdout, input, printer);
+        queue!(stdout, cursor::Show, LeaveAlternateScreen)?;
+        stdout.flush()?;
+        result
+    }
+
+    /// Lines of text on the screen, the last row is the status line.
+    fn height() -> usize {
+        terminal::size()
+            .map_or(24, |(_, rows)| rows as usize)
+            .saturating_sub(1)
+            .max(1)
+    }
+
+    fn scroll(&mut self, lines: isize) {
+        let bottom = self.lines.len().saturating_sub(Pager::height());
+        self.top = self.top.saturating_add_signed(lines).min(bottom);
+    }
+
+    /// The next event, `None` when only a message from another thread came in.
+    fn next_event(
+        &mut self,
+        input: &mut Input,
+        printer: &mut Printer,
+    ) -> io::Result<Option<Event>> {
+        loop {
+            if let Some(event) = input.next(POLL_INTERVAL)? {
+                return Ok(Some(event));
+            }
+            if let Some(message) = printer.hold_external() {
+                self.message = Some(message.to_string());
+                return Ok(None);
+            }
+        }
+    }
+
+    fn event_loop(
+        &mut self,
+        stdout: &mut io::Stdout,
+        input: &mut Input,
+        printer: &mut Printer,
+    ) -> io::Result<()> {
+        let page = || Pager::height() as isize;
+        loop {
+            self.draw(stdout, None)?;
+            let event = match self.next_event(input, printer)? {
+                Some(event) => event,
+                None => continue,
+            };
+            let key = match event {
+                Event::Key(key) if key.kind != KeyEventKind::Release => key,
+                Event::Mouse(mouse) => {
+                    match mouse.kind {
+                        MouseEventKind::ScrollDown => self.scroll(3),
+                        MouseEventKind::ScrollUp => self.scroll(-3),
+                        _ => (),
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            self.message = None;
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                    return Ok(())
+                }
+                KeyCode::Char('j') | KeyCode::Down | KeyCode::Enter => self.scroll(1),
+                KeyCode::Char('k') | KeyCode::Up => self.scroll(-1),
+                KeyCode::Char(' ' | 'f') | KeyCode::PageDown => self.scroll(page()),
+                KeyCode::Char('b') | KeyCode::PageUp => self.scroll(-page()),
+                KeyCode::Char('g') | KeyCode::Home => self.top = 0,
+                KeyCode::Char('G') | KeyCode::End => self.scroll(isize::MAX),
+                KeyCode::Char('/') => {
+                    if let Some(pattern) = self.read_pattern(stdout, input, printer)? {
+                        self.pattern = Some(pattern);
+                        self.find(true);
+                    }
+                }
+                KeyCode::Char('n') => self.find(true),
+                KeyCode::Char('N') => self.find(false),
+                _ => (),
+            }
+        }
+    }
+
+    /// Reads a search pattern in the status line, `None` when it is cancelled.
+    fn read_pattern(
+        &mut self,
+        stdout: &mut io::Stdout,
+        input: &mut Input,
+        printer: &mut Printer,
+    ) -> io::Result<Option<String>> {
+        let mut pattern = String::new();
+        loop {
+            self.draw(stdout, Some(&pattern))?;
+            match self.next_event(input, printer)? {
+                Some(Event::Key(KeyEvent {
+                    code,
+                    kind: KeyEventKind::Press | KeyEventKind::Repeat,
+                    ..
+                })) => match code {
+                    KeyCode::Enter if pattern.is_empty() => return Ok(self.pattern.clone()),
+                    KeyCode::Enter => return Ok(Some(pattern)),
+                    KeyCode::Esc => return Ok(None),
+                    KeyCode::Backspace if pattern.is_empty() => return Ok(None),
+                    KeyCode::Backspace => {
+                        pattern.pop();
+                    }
+                    KeyCode::Char(c) => pattern.push(c),
+                    _ => (),
+                },
+                _ => continue,
+            }
+        }
+    }
+
+    /// Scrolls to the next line with the pattern after or before the first line on the screen.
+    fn find(&mut self, forward: bool) {
+        let pattern = match &self.pattern {
+            Some(pattern) => pattern,
+            None => return,
+        };
+        let matches = |i: &usize| self.lines[*i].contains(pattern.as_str());
+        let found = if forward {
+            (self.top + 1..self.lines.len()).find(matches)
+        } else {
+            (0..self.top).rev().find(matches)
+        };
+        match found {
+            Some(line) => {
+                self.top = 0;
+                self.scroll(line as isize);
+            }
+            None => self.message = Some(format!("Pattern not found: {}", pattern)),
+        }
+    }
+
+    /// Draws the visible lines with the matches highlighted, and the status line below.
+    fn draw(&self, stdout: &mut io::Stdout, typing: Option<&str>) -> io::Result<()> {
+        let (columns, _) = terminal::size()?;
+        let columns = columns as usize;
+        let height = Pager::height();
+        for row in 0..height {
+            queue!(
+                stdout,
+                cursor::MoveTo(0, row as u16),
+                terminal::Clear(ClearType::CurrentLine)
+            )?;
+            let line = match self.lines.get(self.top + row) {
+                Some(line) => fit(line, columns),
+                None => String::from("~"),
+            };
+            let text = match &self.pattern {
+                Some(pattern) if !pattern.is_empty() => line
+                    .split(pattern.as_str())
+                    .collect::<Vec<_>>()
+                    .join(&self.theme.selection.apply(pattern)),
+                _ => line,
+            };
+            queue!(stdout, Print(text))?;
+        }
+
+        let status = match (typing, &self.message) {
+            (Some(pattern), _) => format!("/{}", pattern),
+            (None, Some(message)) => message.clone(),
+            (None, None) => format!(
+                "{}-{} of {}  {}",
+                (self.top + 1).min(self.lines.len()),
+                (self.top + height).min(self.lines.len()),
+                self.lines.len(),
+                HINT
+            ),
+        };
+        let status = fit(&status, columns);
+        let padding = columns.saturating_sub(status.chars().map(|c| c.width().unwrap_or(0)).sum());
+        queue!(
+            stdout,
+            cursor::MoveTo(0, height as u16),
+            Print(
+                self.theme
+                    .status_bar
+                    .apply(&format!("{}{}", status, " ".repeat(padding)))
+            )
+        )?;
+        stdout.flush()
+    }
+}
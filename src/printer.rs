@@ -0,0 +1,414 @@
+use super::currentline::CurrentLine;
+use super::layout::Layout;
+use crossterm::{
+    cursor,
+    event::{DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture},
+    execute, queue,
+    style::{Attribute, Print, SetAttribute},
+    terminal::{
+        self, disable_raw_mode, enable_raw_mode, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+    QueueableCommand,
+};
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+use unicode_width::UnicodeWidthChar;
+
+/// Lines of output kept for the output pane of the full-screen layout.
+const MAX_OUTPUT: usize = 10_000;
+
+/// Turns every bare `\n` into `\r\n`, raw mode does not return the carriage by itself.
+pub fn raw_newlines(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut previous = None;
+    for c in text.chars() {
+        if c == '\n' && previous != Some('\r') {
+            result.push('\r');
+        }
+        result.push(c);
+        previous = Some(c);
+    }
+    result
+}
+
+/// Splits a line of output into screen rows, escape sequences take up no columns.
+fn wrap(line: &str, columns: usize) -> Vec<String> {
+    let mut rows = Vec::new();
+    let mut row = String::new();
+    let mut width = 0;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            row.push(c);
+            if chars.next_if_eq(&'[').is_some() {
+                row.push('[');
+                for c in chars.by_ref() {
+                    row.push(c);
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            }
+            continue;
+        }
+        let w = c.width().unwrap_or(0);
+        if width + w > columns && width > 0 {
+            rows.push(std::mem::take(&mut row));
+            width = 0;
+        }
+        row.push(c);
+        width += w;
+    }
+    rows.push(row);
+    rows
+}
+
+/// Single path for everything that is written to the terminal besides the input line.
+///
+/// The line that is currently edited is erased before the output is written and
+/// redrawn below it afterwards, so output always ends up above the prompt.
+/// A plain printer only writes the text, for terminals in cooked mode.
+///
+/// In the full-screen layout the output goes into a scrollable pane on the alternate
+/// screen instead, with the status bar and the input line pinned below it.
+pub struct Printer {
+    stdout: io::Stdout,
+    plain: bool,
+    sender: Sender<String>,
+    receiver: Receiver<String>,
+    /// Everything that was printed and submitted, shown in the output pane.
+    output: Vec<String>,
+    fullscreen: bool,
+    /// Lines of output below the bottom of the pane.
+    scroll: usize,
+    /// Status bar and other rows drawn below the line instead of over the output.
+    footer: Vec<String>,
+    /// Messages from other threads that came while the pager had the screen.
+    held: Vec<String>,
+}
+impl Printer {
+    pub fn new(interactive: bool) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Printer {
+            stdout: io::stdout(),
+            plain: !interactive,
+            sender,
+            receiver,
+            output: Vec::new(),
+            fullscreen: false,
+            scroll: 0,
+            footer: Vec::new(),
+            held: Vec::new(),
+        }
+    }
+
+    /// Handle for other threads, their messages are printed by `print_external`.
+    pub fn external(&self) -> ExternalPrinter {
+        ExternalPrinter {
+            sender: self.sender.clone(),
+        }
+    }
+
+    /// Prints everything that was sent through an `ExternalPrinter` since the last call.
+    pub fn print_external(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        for message in std::mem::take(&mut self.held) {
+            self.print(line, &message)?;
+        }
+        while let Ok(message) = self.receiver.try_recv() {
+            self.print(line, &message)?;
+        }
+        Ok(())
+    }
+
+    /// Keeps the messages from other threads for `print_external`, returns the newest one.
+    pub fn hold_external(&mut self) -> Option<&str> {
+        let held = self.held.len();
+        self.held.extend(self.receiver.try_iter());
+        self.held[held..].last().map(String::as_str)
+    }
+
+    /// Rows to draw with the next line, the first one is the status bar.
+    pub fn set_footer(&mut self, footer: Vec<String>) {
+        self.footer = footer;
+    }
+
+    /// Rows of the footer after the status bar.
+    fn panel_height(&self) -> u16 {
+        self.footer.len().saturating_sub(1) as u16
+    }
+
+    /// A full-width row in the status bar style.
+    fn bar(line: &CurrentLine, text: &str, columns: usize) -> String {
+        let text = wrap(text, columns).swap_remove(0);
+        let padding = columns.saturating_sub(text.chars().map(|c| c.width().unwrap_or(0)).sum());
+        line.theme()
+            .status_bar
+            .apply(&format!("{}{}", text, " ".repeat(padding)))
+    }
+
+    fn record(&mut self, text: &str) {
+        let added = self.output.len();
+        self.output
+            .extend(text.trim_end_matches('\n').split('\n').map(str::to_string));
+        // Scrolled back output stays where it is.
+        if self.scroll > 0 {
+            self.scroll += self.output.len() - added;
+        }
+        if self.output.len() > MAX_OUTPUT {
+            self.output.drain(..self.output.len() - MAX_OUTPUT);
+            self.scroll = self.scroll.min(self.output.len());
+        }
+    }
+
+    /// Keeps the submitted line in the scrollback and starts an empty line below it.
+    pub fn submit(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        if self.plain {
+            line.clear();
+            line.set_position_start_x();
+            return Ok(());
+        }
+        self.record(&line.transcript());
+        if self.fullscreen {
+            self.scroll = 0;
+            line.clear();
+            line.set_position_start_x();
+            return self.redraw(line);
+        }
+        // The entered line stays on the screen as it was typed, without the ghost text.
+        line.set_suggestion(None);
+        line.display()?;
+        self.stdout.queue(cursor::MoveToColumn(0))?;
+        self.stdout.write_all(b"\n\r")?;
+        self.stdout.flush()?;
+        line.clear();
+        line.set_position_start_x();
+        self.restore(line)
+    }
+
+    pub fn print(&mut self, line: &mut CurrentLine, text: &str) -> io::Result<()> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        if self.plain {
+            writeln!(self.stdout, "{}", text.trim_end_matches('\n'))?;
+            return self.stdout.flush();
+        }
+        self.record(text);
+        if self.fullscreen {
+            return self.redraw(line);
+        }
+        self.stdout.queue(cursor::MoveToColumn(0))?;
+        self.stdout.queue(terminal::Clear(ClearType::FromCursorDown))?;
+        self.stdout.write_all(raw_newlines(text).as_bytes())?;
+        if !text.ends_with('\n') {
+            self.stdout.write_all(b"\r\n")?;
+        }
+        self.stdout.flush()?;
+        self.restore(line)
+    }
+
+    pub fn is_fullscreen(&self) -> bool {
+        self.fullscreen
+    }
+
+    /// Switches between the inline prompt and the full-screen layout.
+    pub fn set_fullscreen(&mut self, fullscreen: bool, line: &mut CurrentLine) -> io::Result<()> {
+        if self.plain || fullscreen == self.fullscreen {
+            return Ok(());
+        }
+        self.fullscreen = fullscreen;
+        self.scroll = 0;
+        if fullscreen {
+            execute!(self.stdout, EnterAlternateScreen)?;
+            self.redraw(line)
+        } else {
+            execute!(self.stdout, LeaveAlternateScreen)?;
+            self.restore(line)
+        }
+    }
+
+    /// Scrolls the output pane, false when it cannot move further or there is no pane.
+    pub fn scroll(&mut self, line: &mut CurrentLine, lines: isize) -> io::Result<bool> {
+        if !self.fullscreen {
+            return Ok(false);
+        }
+        // The first line of output is the highest the pane goes.
+        let top = self.output.len().saturating_sub(self.page_height()?);
+        let scroll = self.scroll.saturating_add_signed(lines).min(top);
+        if scroll == self.scroll {
+            return Ok(false);
+        }
+        self.scroll = scroll;
+        self.redraw(line)?;
+        Ok(true)
+    }
+
+    /// Lines in a page of the output pane.
+    pub fn page_height(&self) -> io::Result<usize> {
+        Ok(usize::from(Layout::current(self.panel_height())?.output).max(1))
+    }
+
+    /// Empties the screen and starts the line at the top, or empties the output pane.
+    pub fn clear_screen(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        line.clear();
+        line.set_position_start_x();
+        if self.fullscreen {
+            self.output.clear();
+            self.scroll = 0;
+            return self.redraw(line);
+        }
+        line.set_position_start_y();
+        self.stdout.queue(cursor::MoveTo(0, 0))?;
+        self.stdout.queue(terminal::Clear(ClearType::All))?;
+        self.draw_line(line)
+    }
+
+    /// Draws the whole full-screen layout again, or only the line for the inline prompt.
+    pub fn redraw(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        if !self.fullscreen {
+            return self.draw_line(line);
+        }
+        let layout = Layout::current(self.panel_height())?;
+        let columns = usize::from(layout.columns);
+        let height = usize::from(layout.output);
+        let end = self.output.len() - self.scroll;
+        let mut rows = Vec::new();
+        for output in self.output[..end].iter().rev() {
+            if rows.len() >= height {
+                break;
+            }
+            rows.splice(0..0, wrap(output, columns));
+        }
+        let rows = &rows[rows.len().saturating_sub(height)..];
+
+        for row in 0..height {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, row as u16),
+                terminal::Clear(ClearType::CurrentLine)
+            )?;
+            if let Some(text) = rows.get(row) {
+                queue!(self.stdout, Print(text), SetAttribute(Attribute::Reset))?;
+            }
+        }
+
+        let mut status = self.footer.first().cloned().unwrap_or_default();
+        if self.scroll > 0 {
+            status += &format!("  {} more lines below", self.scroll);
+        }
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, layout.status),
+            Print(Printer::bar(line, &status, columns)),
+            cursor::MoveTo(0, layout.input + 1),
+            terminal::Clear(ClearType::FromCursorDown)
+        )?;
+        for (i, text) in self.footer.iter().skip(1).enumerate() {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, layout.input + 1 + i as u16),
+                Print(Printer::bar(line, text, columns))
+            )?;
+        }
+        self.stdout.flush()?;
+        line.position.set_y(layout.input);
+        line.display()
+    }
+
+    /// Draws the line with the footer below it, the screen scrolls up when there is no room.
+    fn draw_line(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        let (columns, rows) = terminal::size()?;
+        let bottom = rows.saturating_sub(self.footer.len() as u16 + 1);
+        if line.position.y() > bottom {
+            let up = line.position.y() - bottom;
+            self.stdout.queue(cursor::MoveTo(0, rows - 1))?;
+            self.stdout.write_all("\n".repeat(usize::from(up)).as_bytes())?;
+            line.position.set_y(bottom);
+        }
+        let y = line.position.y();
+        queue!(
+            self.stdout,
+            cursor::MoveTo(0, y + 1),
+            terminal::Clear(ClearType::FromCursorDown)
+        )?;
+        for (i, text) in self.footer.iter().enumerate() {
+            queue!(
+                self.stdout,
+                cursor::MoveTo(0, y + 1 + i as u16),
+                Print(Printer::bar(line, text, usize::from(columns)))
+            )?;
+        }
+        self.stdout.flush()?;
+        line.display()
+    }
+
+    /// Hands the terminal over to a child process, in cooked mode below the output.
+    pub fn suspend(&mut self) -> io::Result<()> {
+        if self.plain {
+            return Ok(());
+        }
+        if self.fullscreen {
+            self.stdout.queue(LeaveAlternateScreen)?;
+        }
+        queue!(
+            self.stdout,
+            cursor::MoveToColumn(0),
+            terminal::Clear(ClearType::FromCursorDown),
+            cursor::SetCursorStyle::DefaultUserShape,
+            DisableMouseCapture,
+            DisableBracketedPaste
+        )?;
+        self.stdout.flush()?;
+        disable_raw_mode()
+    }
+
+    /// Takes the terminal back after `suspend` and redraws the line below what the child printed.
+    pub fn resume(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        if self.plain {
+            return Ok(());
+        }
+        enable_raw_mode()?;
+        let style = if line.overwriting() {
+            cursor::SetCursorStyle::BlinkingUnderScore
+        } else {
+            cursor::SetCursorStyle::BlinkingBlock
+        };
+        queue!(self.stdout, EnableMouseCapture, EnableBracketedPaste, style)?;
+        if self.fullscreen {
+            self.stdout.queue(EnterAlternateScreen)?;
+            return self.redraw(line);
+        }
+        if matches!(cursor::position(), Ok((x, _)) if x != 0) {
+            self.stdout.write_all(b"\r\n")?;
+        }
+        self.stdout.flush()?;
+        self.restore(line)
+    }
+
+    fn restore(&mut self, line: &mut CurrentLine) -> io::Result<()> {
+        if let Ok((_, y)) = cursor::position() {
+            line.position.set_y(y);
+        }
+        self.draw_line(line)
+    }
+}
+impl Drop for Printer {
+    fn drop(&mut self) {
+        if self.fullscreen {
+            let _ = execute!(self.stdout, LeaveAlternateScreen);
+        }
+    }
+}
+
+/// Cloneable, `Send` handle to print above the prompt from background threads.
+#[derive(Clone)]
+pub struct ExternalPrinter {
+    sender: Sender<String>,
+}
+impl ExternalPrinter {
+    /// Returns false when the editor is gone and the message was dropped.
+    pub fn print(&self, message: impl Into<String>) -> bool {
+        self.sender.send(message.into()).is_ok()
+    }
+}
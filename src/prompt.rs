@@ -0,0 +1,142 @@
+use super::capabilities::ColorDepth;
+use crossterm::style::{Attribute, Color, SetAttribute, SetBackgroundColor, SetForegroundColor};
+use std::collections::BTreeMap;
+use std::env;
+use unicode_width::UnicodeWidthStr;
+
+pub const DEFAULT_PROMPT: &str = "> ";
+
+/// A prompt ready to print, `width` counts the columns without escape sequences.
+#[derive(Clone, Debug, Default)]
+pub struct Rendered {
+    pub text: String,
+    pub width: u16,
+}
+impl Rendered {
+    pub fn plain(text: &str) -> Self {
+        Rendered {
+            text: text.to_string(),
+            width: text.width() as u16,
+        }
+    }
+}
+
+/// Values for the variables a prompt template can use.
+pub struct PromptContext<'a> {
+    /// Status of the last command, 0 means success.
+    pub status: i32,
+    /// Number of lines entered in this session.
+    pub count: usize,
+    /// Colors in the template are mapped to these, or dropped.
+    pub colors: ColorDepth,
+    /// Session variables, the built-in names take precedence.
+    pub variables: &'a BTreeMap<String, String>,
+}
+
+fn cwd() -> String {
+    let cwd = match env::current_dir() {
+        Ok(cwd) => cwd.display().to_string(),
+        Err(_) => return String::from("?"),
+    };
+    match env::var("HOME") {
+        Ok(home) if !home.is_empty() && cwd.starts_with(&home) => {
+            format!("~{}", &cwd[home.len()..])
+        }
+        _ => cwd,
+    }
+}
+
+/// Escape sequence for a style name, empty for a color when colors are off.
+fn style(name: &str, colors: ColorDepth) -> Option<String> {
+    let attribute = match name {
+        "reset" => Some(Attribute::Reset),
+        "bold" => Some(Attribute::Bold),
+        "dim" => Some(Attribute::Dim),
+        "italic" => Some(Attribute::Italic),
+        "underline" => Some(Attribute::Underlined),
+        "reverse" => Some(Attribute::Reverse),
+        _ => None,
+    };
+    if let Some(attribute) = attribute {
+        return Some(SetAttribute(attribute).to_string());
+    }
+    match name.strip_prefix("on_") {
+        Some(color) => Color::try_from(color).ok().map(|color| {
+            colors
+                .map(color)
+                .map_or(String::new(), |color| SetBackgroundColor(color).to_string())
+        }),
+        None => Color::try_from(name).ok().map(|color| {
+            colors
+                .map(color)
+                .map_or(String::new(), |color| SetForegroundColor(color).to_string())
+        }),
+    }
+}
+
+impl PromptContext<'_> {
+    fn variable(&self, name: &str) -> Option<String> {
+        match name {
+            "cwd" => Some(cwd()),
+            "time" => Some(chrono::Local::now().format("%H:%M:%S").to_string()),
+            "status" => Some(self.status.to_string()),
+            "count" => Some(self.count.to_string()),
+            _ => self.variables.get(name).cloned(),
+        }
+    }
+
+    /// Expands `{variable}` and `{style}` in `template`, `{{` and `}}` are literal braces.
+    ///
+    /// Variables are `cwd`, `time`, `status`, `count` and the session variables. Styles are `bold`, `dim`,
+    /// `italic`, `underline`, `reverse`, `reset`, color names like `red` or
+    /// `dark_cyan` and `on_<color>` for the background. Unknown names are kept as they are.
+    pub fn render(&self, template: &str) -> Rendered {
+        let mut text = String::new();
+        let mut width = 0;
+        let mut rest = template;
+        while let Some(open) = rest.find(['{', '}']) {
+            let literal = &rest[..open];
+            text += literal;
+            width += literal.width();
+            rest = &rest[open..];
+
+            if let Some(after) = rest.strip_prefix("{{").or(rest.strip_prefix("}}")) {
+                text += &rest[..1];
+                width += 1;
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix('}') {
+                text.push('}');
+                width += 1;
+                rest = after;
+                continue;
+            }
+            let close = match rest.find('}') {
+                Some(close) => close,
+                None => break,
+            };
+            let name = &rest[1..close];
+            if let Some(value) = self.variable(name) {
+                width += value.width();
+                text += &value;
+            } else if let Some(style) = style(name, self.colors) {
+                text += &style;
+            } else {
+                text += &rest[..=close];
+                width += rest[..=close].width();
+            }
+            rest = &rest[close + 1..];
+        }
+        text += rest;
+        width += rest.width();
+        if text.contains('\x1b') {
+            text += &SetAttribute(Attribute::Reset).to_string();
+        }
+
+        Rendered {
+            text,
+            width: width as u16,
+        }
+    }
+}
@@ -0,0 +1,136 @@
+use crossterm::event::{self, Event};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, LineWriter, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How long the readers of `Input` wait for an event before they look at other work.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// One line of a recording, `millis` counts from the start of the session.
+#[derive(Serialize, Deserialize)]
+struct RecordedEvent {
+    millis: u64,
+    event: Event,
+}
+
+/// Appends every event as a JSON line, flushed per event so crashed sessions are kept.
+pub struct Recorder {
+    file: LineWriter<File>,
+    start: Instant,
+}
+impl Recorder {
+    pub fn create(path: &str) -> io::Result<Self> {
+        Ok(Recorder {
+            file: LineWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    pub fn record(&mut self, event: &Event) -> io::Result<()> {
+        let recorded = RecordedEvent {
+            millis: self.start.elapsed().as_millis() as u64,
+            event: event.clone(),
+        };
+        serde_json::to_writer(&mut self.file, &recorded)?;
+        self.file.write_all(b"\n")
+    }
+}
+
+pub struct Replay {
+    events: VecDeque<RecordedEvent>,
+    realtime: bool,
+    start: Instant,
+}
+impl Replay {
+    /// Reads a file written by `Recorder`, with `realtime` the recorded pauses are kept.
+    pub fn open(path: &str, realtime: bool) -> io::Result<Self> {
+        let mut events = VecDeque::new();
+        for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let recorded = serde_json::from_str(&line).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}:{}: {}", path, number + 1, e),
+                )
+            })?;
+            events.push_back(recorded);
+        }
+        Ok(Replay {
+            events,
+            realtime,
+            start: Instant::now(),
+        })
+    }
+
+    fn next(&mut self, timeout: Duration) -> Option<Event> {
+        if self.realtime {
+            let due = Duration::from_millis(self.events.front()?.millis);
+            let elapsed = self.start.elapsed();
+            if due > elapsed + timeout {
+                thread::sleep(timeout);
+                return None;
+            }
+            thread::sleep(due.saturating_sub(elapsed));
+        }
+        self.events.pop_front().map(|recorded| recorded.event)
+    }
+
+    fn is_done(&self) -> bool {
+        self.events.is_empty()
+    }
+}
+
+/// Where `read_char` takes its events from, a replay hands over to the terminal once it is done.
+pub enum EventSource {
+    Terminal,
+    Replay(Replay),
+}
+impl EventSource {
+    /// Waits at most `timeout` for the next event.
+    pub fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        if let EventSource::Replay(replay) = self {
+            if replay.is_done() {
+                *self = EventSource::Terminal;
+            } else {
+                return Ok(replay.next(timeout));
+            }
+        }
+        if event::poll(timeout)? {
+            event::read().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// The events of a session, everything that reads keys goes through it so a
+/// recording has all of them and a replay reaches every reader.
+pub struct Input {
+    source: EventSource,
+    recorder: Option<Recorder>,
+}
+impl Input {
+    pub fn new(source: EventSource, recorder: Option<Recorder>) -> Self {
+        Input { source, recorder }
+    }
+
+    /// Waits at most `timeout` for the next event and records it.
+    pub fn next(&mut self, timeout: Duration) -> io::Result<Option<Event>> {
+        let event = self.source.next(timeout)?;
+        if let (Some(event), Some(recorder)) = (&event, self.recorder.as_mut()) {
+            recorder.record(event)?;
+        }
+        Ok(event)
+    }
+}
+impl Default for Input {
+    fn default() -> Self {
+        Input::new(EventSource::Terminal, None)
+    }
+}
@@ -0,0 +1,30 @@
+use std::time::{Duration, Instant};
+
+/// How long a message stays in the status bar.
+const MESSAGE_DURATION: Duration = Duration::from_secs(3);
+
+/// Short messages for the status bar, like why a key did nothing, that go away by themselves.
+#[derive(Default)]
+pub struct StatusBar {
+    message: Option<(String, Instant)>,
+}
+impl StatusBar {
+    pub fn flash(&mut self, message: &str) {
+        self.message = Some((message.to_string(), Instant::now()));
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_ref().map(|(message, _)| message.as_str())
+    }
+
+    /// Drops the message once it was shown long enough, true when the bar has to be redrawn.
+    pub fn expire(&mut self) -> bool {
+        match &self.message {
+            Some((_, shown)) if shown.elapsed() >= MESSAGE_DURATION => {
+                self.message = None;
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use super::history::History;
+use super::my_parser::COMMANDS;
+use std::collections::BTreeMap;
+
+/// What suggesters can draw from.
+pub struct Sources<'a> {
+    pub history: &'a History,
+    pub aliases: &'a BTreeMap<String, String>,
+}
+
+/// Source of the ghost text shown after the input line.
+pub trait Suggester {
+    /// Returns the rest of a suggested line that starts with `line`.
+    fn suggest(&self, line: &str, sources: &Sources) -> Option<String>;
+}
+
+/// Suggests the most recent history entry starting with the line.
+pub struct HistorySuggester;
+impl Suggester for HistorySuggester {
+    fn suggest(&self, line: &str, sources: &Sources) -> Option<String> {
+        sources
+            .history
+            .entries()
+            .iter()
+            .rev()
+            .find(|entry| entry.len() > line.len() && entry.starts_with(line))
+            .map(|entry| entry[line.len()..].to_string())
+    }
+}
+
+/// Completes the name of a command or alias while it is the only word on the line.
+pub struct CommandSuggester;
+impl Suggester for CommandSuggester {
+    fn suggest(&self, line: &str, sources: &Sources) -> Option<String> {
+        let name = line.strip_prefix(':')?;
+        if name.contains(char::is_whitespace) {
+            return None;
+        }
+        COMMANDS
+            .iter()
+            .copied()
+            .chain(sources.aliases.keys().map(String::as_str))
+            .find(|command| command.len() > name.len() && command.starts_with(name))
+            .map(|command| command[name.len()..].to_string())
+    }
+}
@@ -0,0 +1,203 @@
+use super::capabilities::ColorDepth;
+use crossterm::style::{Attribute, Color, ContentStyle, Stylize};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Colors and attributes of a role, unset colors keep the terminal default.
+///
+/// Colors are written like `"red"`, `"dark_grey"`, `"ansi_(208)"`, `"rgb_(255,128,0)"` or `"#ff8000"`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+impl Style {
+    fn fg(color: Color) -> Self {
+        Style {
+            foreground: Some(color),
+            ..Style::default()
+        }
+    }
+
+    fn on(mut self, color: Color) -> Self {
+        self.background = Some(color);
+        self
+    }
+
+    fn bold(mut self) -> Self {
+        self.bold = true;
+        self
+    }
+
+    fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    fn reverse() -> Self {
+        Style {
+            reverse: true,
+            ..Style::default()
+        }
+    }
+
+    /// The same style with colors the terminal can show.
+    ///
+    /// Without colors a background turns into reverse video, so bars and selections stay visible.
+    fn downgrade(&self, colors: ColorDepth) -> Self {
+        Style {
+            foreground: self.foreground.and_then(|color| colors.map(color)),
+            background: self.background.and_then(|color| colors.map(color)),
+            reverse: self.reverse || (colors == ColorDepth::None && self.background.is_some()),
+            ..self.clone()
+        }
+    }
+
+    /// `base` with the colors and attributes of this style on top.
+    pub fn patch(&self, mut base: ContentStyle) -> ContentStyle {
+        if let Some(color) = self.foreground {
+            base.foreground_color = Some(color);
+        }
+        if let Some(color) = self.background {
+            base.background_color = Some(color);
+        }
+        let attributes = [
+            (self.bold, Attribute::Bold),
+            (self.dim, Attribute::Dim),
+            (self.italic, Attribute::Italic),
+            (self.underline, Attribute::Underlined),
+            (self.reverse, Attribute::Reverse),
+        ];
+        for (set, attribute) in attributes {
+            if set {
+                base = base.attribute(attribute);
+            }
+        }
+        base
+    }
+
+    pub fn content(&self) -> ContentStyle {
+        self.patch(ContentStyle::new())
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        self.content().apply(text).to_string()
+    }
+}
+
+/// Styles for everything the editor draws, roles missing in a user theme come from `dark`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    pub prompt: Style,
+    /// Typed text that is not part of a command.
+    pub input: Style,
+    pub command: Style,
+    pub argument: Style,
+    pub flag: Style,
+    pub string: Style,
+    pub path: Style,
+    /// Unknown commands, unterminated strings and error messages.
+    pub error: Style,
+    /// Suggestions shown after the cursor.
+    pub hint: Style,
+    /// The region between the mark and the cursor.
+    pub selection: Style,
+    pub status_bar: Style,
+}
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::dark()
+    }
+}
+impl Theme {
+    pub const BUILT_IN: [&'static str; 3] = ["dark", "light", "high-contrast"];
+
+    pub fn dark() -> Self {
+        Theme {
+            prompt: Style::default(),
+            input: Style::default(),
+            command: Style::fg(Color::Cyan).bold(),
+            argument: Style::default(),
+            flag: Style::fg(Color::Yellow),
+            string: Style::fg(Color::Green),
+            path: Style::fg(Color::Blue).underline(),
+            error: Style::fg(Color::Red),
+            hint: Style::fg(Color::DarkGrey),
+            selection: Style::reverse(),
+            status_bar: Style::fg(Color::Black).on(Color::Cyan),
+        }
+    }
+
+    pub fn light() -> Self {
+        Theme {
+            prompt: Style::default(),
+            input: Style::default(),
+            command: Style::fg(Color::DarkBlue).bold(),
+            argument: Style::default(),
+            flag: Style::fg(Color::DarkMagenta),
+            string: Style::fg(Color::DarkGreen),
+            path: Style::fg(Color::DarkBlue).underline(),
+            error: Style::fg(Color::DarkRed),
+            hint: Style::fg(Color::Grey),
+            selection: Style::reverse(),
+            status_bar: Style::fg(Color::White).on(Color::DarkBlue),
+        }
+    }
+
+    pub fn high_contrast() -> Self {
+        Theme {
+            prompt: Style::default().bold(),
+            input: Style::fg(Color::White),
+            command: Style::fg(Color::Yellow).bold(),
+            argument: Style::fg(Color::White),
+            flag: Style::fg(Color::Cyan).bold(),
+            string: Style::fg(Color::Green).bold(),
+            path: Style::fg(Color::White).underline(),
+            error: Style::fg(Color::White).on(Color::DarkRed).bold(),
+            hint: Style::fg(Color::Grey).italic(),
+            selection: Style::fg(Color::Black).on(Color::Yellow),
+            status_bar: Style::fg(Color::Black).on(Color::White),
+        }
+    }
+
+    pub fn downgrade(&self, colors: ColorDepth) -> Self {
+        Theme {
+            prompt: self.prompt.downgrade(colors),
+            input: self.input.downgrade(colors),
+            command: self.command.downgrade(colors),
+            argument: self.argument.downgrade(colors),
+            flag: self.flag.downgrade(colors),
+            string: self.string.downgrade(colors),
+            path: self.path.downgrade(colors),
+            error: self.error.downgrade(colors),
+            hint: self.hint.downgrade(colors),
+            selection: self.selection.downgrade(colors),
+            status_bar: self.status_bar.downgrade(colors),
+        }
+    }
+
+    /// A theme from `themes` or a built-in one, user themes win.
+    pub fn named(name: &str, themes: &BTreeMap<String, Theme>) -> Option<Theme> {
+        if let Some(theme) = themes.get(name) {
+            return Some(theme.clone());
+        }
+        match name {
+            "dark" => Some(Theme::dark()),
+            "light" => Some(Theme::light()),
+            "high-contrast" => Some(Theme::high_contrast()),
+            _ => None,
+        }
+    }
+}
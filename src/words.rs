@@ -0,0 +1,118 @@
+use serde::{Deserialize, Serialize};
+
+/// Decides where words start and end for word motions and word edits.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum WordStyle {
+    /// Letters and digits, like readline.
+    #[default]
+    Readline,
+    /// Like vi `w`: letters, digits and `_`, or runs of other characters.
+    ViWord,
+    /// Like vi `W`: everything between whitespace.
+    ViBigWord,
+    /// Everything between whitespace and the given characters.
+    Separators(String),
+}
+impl WordStyle {
+    /// Words are runs of characters of the same class, class 0 separates them.
+    fn class(&self, c: char) -> u8 {
+        match self {
+            WordStyle::Readline => u8::from(c.is_alphanumeric()),
+            WordStyle::ViWord if c.is_whitespace() => 0,
+            WordStyle::ViWord if c.is_alphanumeric() || c == '_' => 1,
+            WordStyle::ViWord => 2,
+            WordStyle::ViBigWord => u8::from(!c.is_whitespace()),
+            WordStyle::Separators(separators) => {
+                u8::from(!c.is_whitespace() && !separators.contains(c))
+            }
+        }
+    }
+
+    /// Words as `(start, end)` character ranges.
+    pub fn spans(&self, chars: &[char]) -> Vec<(usize, usize)> {
+        let mut spans: Vec<(usize, usize)> = Vec::new();
+        for (i, &c) in chars.iter().enumerate() {
+            let class = self.class(c);
+            if class == 0 {
+                continue;
+            }
+            match spans.last_mut() {
+                Some((start, end)) if *end == i && self.class(chars[*start]) == class => *end += 1,
+                _ => spans.push((i, i + 1)),
+            }
+        }
+        spans
+    }
+
+    /// Start of the word in front of `x`.
+    pub fn previous_start(&self, chars: &[char], x: usize) -> Option<usize> {
+        self.spans(chars)
+            .iter()
+            .rev()
+            .find(|&&(start, _)| start < x)
+            .map(|&(start, _)| start)
+    }
+
+    /// Start of the word after `x`, or the end of the line.
+    pub fn next_start(&self, chars: &[char], x: usize) -> usize {
+        self.spans(chars)
+            .iter()
+            .find(|&&(start, _)| start > x)
+            .map_or(chars.len(), |&(start, _)| start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(style: WordStyle, line: &str) -> Vec<(usize, usize)> {
+        style.spans(&line.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn spans_follow_the_style() {
+        let line = "foo_bar.baz  x-1";
+        assert_eq!(
+            spans(WordStyle::Readline, line),
+            vec![(0, 3), (4, 7), (8, 11), (13, 14), (15, 16)]
+        );
+        assert_eq!(
+            spans(WordStyle::ViWord, line),
+            vec![(0, 7), (7, 8), (8, 11), (13, 14), (14, 15), (15, 16)]
+        );
+        assert_eq!(spans(WordStyle::ViBigWord, line), vec![(0, 11), (13, 16)]);
+        assert_eq!(
+            spans(WordStyle::Separators(String::from(".-")), line),
+            vec![(0, 7), (8, 11), (13, 14), (15, 16)]
+        );
+    }
+
+    #[test]
+    fn spans_of_blank_lines_are_empty() {
+        assert_eq!(spans(WordStyle::Readline, ""), vec![]);
+        assert_eq!(spans(WordStyle::ViWord, "   "), vec![]);
+    }
+
+    #[test]
+    fn next_start_skips_to_the_following_word() {
+        let chars = "ab  cd.ef".chars().collect::<Vec<_>>();
+        let style = WordStyle::Readline;
+        assert_eq!(style.next_start(&chars, 0), 4);
+        assert_eq!(style.next_start(&chars, 2), 4);
+        assert_eq!(style.next_start(&chars, 4), 7);
+        // The last word goes to the end of the line.
+        assert_eq!(style.next_start(&chars, 7), 9);
+        assert_eq!(style.next_start(&[], 0), 0);
+    }
+
+    #[test]
+    fn previous_start_goes_back_a_word() {
+        let chars = "ab  cd.ef".chars().collect::<Vec<_>>();
+        let style = WordStyle::ViWord;
+        assert_eq!(style.previous_start(&chars, 9), Some(7));
+        assert_eq!(style.previous_start(&chars, 7), Some(6));
+        assert_eq!(style.previous_start(&chars, 4), Some(0));
+        assert_eq!(style.previous_start(&chars, 0), None);
+    }
+}